@@ -1,6 +1,13 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 68K
+
+    /* Saved runtime settings; keep in step with SETTINGS_OFFSET    */
+    SETTINGS : ORIGIN = 0x101EF000, LENGTH = 4K
+
+    /* Blackbox event log, written at runtime; keep in step with   */
+    /* BLACKBOX_OFFSET and BLACKBOX_SIZE in rip_core::config       */
+    BLACKBOX : ORIGIN = 0x101F0000, LENGTH = 64K
 
     /* Pick one of the two options for RAM layout     */
 
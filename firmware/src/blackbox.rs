@@ -0,0 +1,148 @@
+//! Flash blackbox task (Core 0)
+//!
+//! Logs state changes, battery and current extremes and the previous boot's
+//! panic into the `BLACKBOX` region of flash, for reading back after a match
+//! with `rip_blackbox`. See `rip_core::blackbox` for the format.
+//!
+//! Programming flash stalls execute-in-place on both cores: a 16-byte entry
+//! costs well under a millisecond, but a sector erase takes tens of them. So
+//! erases only happen while the drive is disarmed; an event that would need
+//! one mid-fight is dropped and counted instead.
+//!
+//! The task owns the flash, so it also saves settings for the USB console,
+//! under the same rule.
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::Receiver;
+use embassy_time::{Duration, Instant, Ticker};
+
+use rip_core::battery::BatteryStatus;
+use rip_core::blackbox::{is_erased, Entry, Event, Recorder, Ring, ENTRY_SIZE, SECTOR_SIZE};
+use rip_core::config::*;
+use rip_core::current::DriveCurrent;
+use rip_core::panic_record::PanicRecord;
+use rip_core::settings::Settings;
+use rip_core::state::{BotState, Status};
+
+use crate::hardware::SystemFlash;
+use crate::settings::save_settings;
+
+struct Blackbox {
+    flash: SystemFlash,
+    ring: Ring,
+    dropped: u32,
+}
+
+impl Blackbox {
+    /// Scan the region for the newest entry and carry on after it
+    fn new(mut flash: SystemFlash) -> Self {
+        let slots = BLACKBOX_SIZE as usize / ENTRY_SIZE;
+        let ring = Ring::recover(
+            BLACKBOX_SIZE as usize,
+            (0..slots).map(|slot| {
+                let mut bytes = [0; ENTRY_SIZE];
+                let offset = BLACKBOX_OFFSET + (slot * ENTRY_SIZE) as u32;
+                flash.blocking_read(offset, &mut bytes).ok()?;
+                Entry::decode(&bytes).map(|entry| entry.sequence)
+            }),
+        );
+        info!("Blackbox resuming at offset {}", ring.offset());
+
+        Blackbox { flash, ring, dropped: 0 }
+    }
+
+    /// Log an event, first owning up to any that were dropped
+    fn record(&mut self, event: Event, may_erase: bool) {
+        if self.dropped > 0 && self.write(Event::Dropped(self.dropped), may_erase) {
+            self.dropped = 0;
+        }
+        if !self.write(event, may_erase) {
+            self.dropped += 1;
+        }
+    }
+
+    fn write(&mut self, event: Event, may_erase: bool) -> bool {
+        // A slot torn by a power cut is neither valid nor erased; skip it
+        while !self.ring.needs_erase() && !self.slot_erased() {
+            self.ring.skip();
+        }
+
+        let offset = BLACKBOX_OFFSET + self.ring.offset() as u32;
+        if self.ring.needs_erase() {
+            if !may_erase {
+                return false;
+            }
+            if let Err(e) = self.flash.blocking_erase(offset, offset + SECTOR_SIZE as u32) {
+                warn!("Blackbox erase failed: {:?}", e);
+                return false;
+            }
+        }
+
+        let entry = self.ring.push(Instant::now().as_millis() as u32, event);
+        match self.flash.blocking_write(offset, &entry.encode()) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Blackbox write failed: {:?}", e);
+                false
+            }
+        }
+    }
+
+    fn slot_erased(&mut self) -> bool {
+        let mut bytes = [0; ENTRY_SIZE];
+        let offset = BLACKBOX_OFFSET + self.ring.offset() as u32;
+        self.flash.blocking_read(offset, &mut bytes).is_ok() && is_erased(&bytes)
+    }
+}
+
+#[embassy_executor::task]
+pub async fn blackbox_task(
+    flash: SystemFlash,
+    panic: Option<PanicRecord>,
+    mut status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    mut battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    mut current_receiver: Receiver<'static, CriticalSectionRawMutex, DriveCurrent, 3>,
+    save_request: &'static Signal<CriticalSectionRawMutex, Settings>,
+    save_result: &'static Signal<CriticalSectionRawMutex, bool>,
+) {
+    info!("Blackbox task starting...");
+
+    // Nothing is armed yet at boot, so erasing is fine
+    let mut blackbox = Blackbox::new(flash);
+    blackbox.record(Event::Boot, true);
+    if let Some(record) = panic {
+        blackbox.record(Event::Panic { core: record.core, line: record.line }, true);
+    }
+
+    let mut recorder = Recorder::new();
+    let mut ticker = Ticker::every(Duration::from_millis(BLACKBOX_POLL_MS));
+    let mut may_erase = true;
+
+    loop {
+        ticker.next().await;
+
+        if let Some(battery) = battery_receiver.try_changed() {
+            recorder.battery(battery.millivolts);
+        }
+        // Filtered average: polled here, a single-sample peak would be luck
+        if let Some(current) = current_receiver.try_changed() {
+            recorder.current(current.left.average_ma.max(current.right.average_ma));
+        }
+        if let Some(status) = status_receiver.try_changed() {
+            may_erase = !matches!(status.state, BotState::Arming | BotState::Combat);
+            recorder.status(status, |event| blackbox.record(event, may_erase));
+        }
+
+        if let Some(settings) = save_request.try_take() {
+            let saved = may_erase && save_settings(&mut blackbox.flash, &settings).is_ok();
+            if saved {
+                info!("Settings saved: {}", settings);
+            } else {
+                warn!("Settings not saved");
+            }
+            save_result.signal(saved);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! USB serial console (Core 0)
+//!
+//! A line-based console on the first USB serial port for bench work: check state and
+//! controller input, tune settings live, spin each drive side and stop the
+//! bot. Commands are parsed by `rip_core::console`; type `help` for the list.
+//!
+//! Nothing here talks to the actuators directly. Motor tests go through the
+//! state machine on core 1, which only runs them while idle, and saving goes
+//! through the blackbox task, which owns the flash.
+
+use core::fmt::Write;
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::{Receiver, Sender};
+use embassy_time::{with_timeout, Duration, Instant};
+use embassy_usb::driver::EndpointError;
+
+use rip_core::battery::BatteryStatus;
+use rip_core::console::{parse, Command, LineBuffer, MotorTest, HELP};
+use rip_core::current::DriveCurrent;
+use rip_core::events::EmergencyReason;
+use rip_core::input::ControllerData;
+use rip_core::settings::{SettingKey, Settings};
+use rip_core::state::{BotState, Status};
+
+use crate::hardware::{SerialClass, UsbDevice};
+
+/// Longest command line; anything longer is rejected whole
+const LINE_LENGTH: usize = 64;
+
+/// Flash writes are quick, so no answer within this means the save was lost
+const SAVE_TIMEOUT_MS: u64 = 1000;
+
+const PACKET_SIZE: usize = 64;
+
+/// Reply text, cut short rather than failing if it ever outgrows the buffer
+struct Reply {
+    bytes: [u8; 768],
+    len: usize,
+}
+
+impl Reply {
+    const fn new() -> Self {
+        Reply { bytes: [0; 768], len: 0 }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl Write for Reply {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let take = s.len().min(self.bytes.len() - self.len);
+        self.bytes[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+/// Everything the console reads or pokes, bundled for the command handler
+pub struct ConsoleLinks {
+    pub controller_receiver: Receiver<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    pub status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    pub battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    pub current_receiver: Receiver<'static, CriticalSectionRawMutex, DriveCurrent, 3>,
+    pub settings_sender: Sender<'static, CriticalSectionRawMutex, Settings, 2>,
+    pub save_request: &'static Signal<CriticalSectionRawMutex, Settings>,
+    pub save_result: &'static Signal<CriticalSectionRawMutex, bool>,
+    pub motor_test_signal: &'static Signal<CriticalSectionRawMutex, MotorTest>,
+    pub emergency_signal: &'static Signal<CriticalSectionRawMutex, EmergencyReason>,
+}
+
+#[embassy_executor::task]
+pub async fn usb_task(mut usb: UsbDevice) -> ! {
+    usb.run().await
+}
+
+#[embassy_executor::task]
+pub async fn console_task(mut class: SerialClass, mut links: ConsoleLinks) {
+    info!("Console task starting...");
+
+    loop {
+        class.wait_connection().await;
+        info!("Console connected");
+        let _ = session(&mut class, &mut links).await;
+        info!("Console disconnected");
+    }
+}
+
+/// Serve one terminal connection until the host goes away
+async fn session(class: &mut SerialClass, links: &mut ConsoleLinks) -> Result<(), EndpointError> {
+    let mut line = LineBuffer::<LINE_LENGTH>::new();
+    let mut packet = [0; PACKET_SIZE];
+
+    send(class, b"rust_in_peace console, type help\r\n> ").await?;
+    loop {
+        let n = class.read_packet(&mut packet).await?;
+        for &byte in &packet[..n] {
+            let Some(result) = line.push(byte) else {
+                continue;
+            };
+
+            let mut reply = Reply::new();
+            match result.and_then(parse) {
+                Ok(command) => run(command, links, &mut reply).await,
+                Err(e) => {
+                    let _ = writeln!(reply, "error: {}", e.message());
+                }
+            }
+            let _ = write!(reply, "> ");
+            send(class, reply.as_bytes()).await?;
+        }
+    }
+}
+
+/// Write a reply in packets, translating line endings for the terminal
+async fn send(class: &mut SerialClass, text: &[u8]) -> Result<(), EndpointError> {
+    let mut packet = [0; PACKET_SIZE];
+    let mut len = 0;
+    for &byte in text {
+        // Room for "\r\n" so a line ending never splits across packets
+        if len >= PACKET_SIZE - 1 {
+            class.write_packet(&packet[..len]).await?;
+            len = 0;
+        }
+        if byte == b'\n' {
+            packet[len] = b'\r';
+            len += 1;
+        }
+        packet[len] = byte;
+        len += 1;
+    }
+    class.write_packet(&packet[..len]).await?;
+
+    // A full last packet needs a zero-length one to end the transfer
+    if len == PACKET_SIZE {
+        class.write_packet(&[]).await?;
+    }
+    Ok(())
+}
+
+async fn run(command: Command, links: &mut ConsoleLinks, out: &mut Reply) {
+    let status = links.status_receiver.try_get();
+    let settings = links.settings_sender.try_get().unwrap_or(Settings::DEFAULT);
+
+    match command {
+        Command::Help => {
+            let _ = out.write_str(HELP);
+        }
+        Command::Status => {
+            let _ = writeln!(out, "uptime    {} ms", Instant::now().as_millis());
+            match status {
+                Some(status) => {
+                    let _ = writeln!(out, "state     {:?}", status.state);
+                    let _ = writeln!(out, "weapon    {:?}", status.weapon);
+                }
+                None => {
+                    let _ = writeln!(out, "state     (core 1 has not reported)");
+                }
+            }
+            if let Some(battery) = links.battery_receiver.try_get() {
+                let _ = writeln!(out, "battery   {} mV ({:?})", battery.millivolts, battery.level);
+            }
+            if let Some(current) = links.current_receiver.try_get() {
+                for (side, status) in [("left", current.left), ("right", current.right)] {
+                    let _ = writeln!(
+                        out,
+                        "current   {:<5} {} mA, avg {} mA, limit {}%{}",
+                        side,
+                        status.milliamps,
+                        status.average_ma,
+                        status.duty_limit,
+                        if status.stalled { ", STALLED" } else { "" }
+                    );
+                }
+            }
+        }
+        Command::Input => match links.controller_receiver.try_get() {
+            Some(data) => {
+                let _ = writeln!(
+                    out,
+                    "frame {} at {} ms ({} ms ago)",
+                    data.sequence,
+                    data.timestamp_ms,
+                    Instant::now().as_millis().saturating_sub(data.timestamp_ms)
+                );
+                let _ = writeln!(out, "left  x {:>4} y {:>4}", data.left_x, data.left_y);
+                let _ = writeln!(out, "right x {:>4} y {:>4}", data.right_x, data.right_y);
+                let _ = writeln!(out, "triggers {:>3} {:>3}", data.left_trigger, data.right_trigger);
+                let _ = writeln!(out, "buttons {:#06x}", data.buttons.bits());
+                let _ = write!(out, "link {}%", data.link.percent);
+                if let Some(rssi) = data.link.rssi_dbm {
+                    let _ = write!(out, ", {} dBm", rssi);
+                }
+                let _ = writeln!(out, "{}", if data.link.failsafe { ", FAILSAFE" } else { "" });
+            }
+            None => {
+                let _ = writeln!(out, "no controller data yet");
+            }
+        },
+        Command::Get(Some(key)) => {
+            let _ = writeln!(out, "{} = {}", key.name(), key.get(&settings));
+        }
+        Command::Get(None) => {
+            for key in SettingKey::ALL {
+                let _ = writeln!(out, "{} = {}", key.name(), key.get(&settings));
+            }
+        }
+        Command::Set(key, value) => {
+            let mut new = settings;
+            match key.set(&mut new, value) {
+                Ok(()) => {
+                    links.settings_sender.send(new);
+                    info!("Console set {} = {}", key.name(), value);
+                    let _ = writeln!(out, "{} = {}", key.name(), value);
+                    if key == SettingKey::Ps2SpiFrequency {
+                        let _ = writeln!(out, "takes effect after save and reset");
+                    }
+                }
+                Err(_) => {
+                    let _ = writeln!(out, "error: {} out of range, unchanged", value);
+                }
+            }
+        }
+        Command::Save => {
+            // Erasing stalls both cores, so never while the drive is live
+            if status.is_some_and(|s| matches!(s.state, BotState::Arming | BotState::Combat)) {
+                let _ = writeln!(out, "error: disarm before saving");
+                return;
+            }
+            links.save_result.reset();
+            links.save_request.signal(settings);
+            match with_timeout(Duration::from_millis(SAVE_TIMEOUT_MS), links.save_result.wait()).await {
+                Ok(true) => {
+                    let _ = writeln!(out, "saved");
+                }
+                Ok(false) => {
+                    let _ = writeln!(out, "error: save failed");
+                }
+                Err(_) => {
+                    let _ = writeln!(out, "error: save timed out");
+                }
+            }
+        }
+        Command::MotorTest(test) => {
+            if status.map(|s| s.state) != Some(BotState::Idle) {
+                let _ = writeln!(out, "error: motor tests only run while idle");
+                return;
+            }
+            let (left, right) = test.sides();
+            links.motor_test_signal.signal(test);
+            let _ = writeln!(out, "motor test L={} R={} for {} ms", left, right, test.duration_ms);
+        }
+        Command::Stop => {
+            warn!("Emergency stop from console");
+            links.emergency_signal.signal(EmergencyReason::ConsoleStop);
+            let _ = writeln!(out, "emergency stop");
+        }
+    }
+}
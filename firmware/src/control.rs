@@ -0,0 +1,365 @@
+//! State controller task - drives the `rip_core` state machine ("brains")
+//! from PS2 input and fault triggers. Also contains hardware driver tasks
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_rp::gpio::{Level, Output};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::{Receiver, Sender};
+use embassy_sync::signal::Signal;
+use embassy_sync::watch;
+use embassy_time::{Duration, Instant, Ticker};
+
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::console::MotorTest;
+use rip_core::current::DriveCurrent;
+use rip_core::events::{EmergencyReason, LedEvent, ServoEvent, TankDriveEvent, WeaponEvent};
+use rip_core::imu::{FlipDetector, Orientation};
+use rip_core::input::{ControllerData, SampleTracker};
+use rip_core::settings::Settings;
+use rip_core::state::{Outputs, StateMachine, Status};
+use rip_core::telemetry::DriveOutput;
+use rip_core::timing::LoopStats;
+use rip_core::watchdog::WatchedTask;
+
+use crate::hardware::{PeripheralsMotor, PeripheralsStateLed};
+use crate::hardware::{new_tank_drive, ServoController, Weapon};
+use crate::safety::{check_in, panicked_core, HeartbeatMonitor, ACTUATION_HEARTBEAT, INPUT_HEARTBEAT};
+
+/// Forward one state machine step to the driver tasks
+async fn send_outputs(
+    outputs: Outputs,
+    tank_sender: &Sender<'static, CriticalSectionRawMutex, TankDriveEvent, 8>,
+    servo_sender: &Sender<'static, CriticalSectionRawMutex, ServoEvent, 8>,
+    weapon_sender: &Sender<'static, CriticalSectionRawMutex, WeaponEvent, 8>,
+    led_sender: &Sender<'static, CriticalSectionRawMutex, LedEvent, 8>,
+) {
+    // Weapon first, so a kill never waits behind the other drivers' queues
+    if let Some(event) = outputs.weapon {
+        weapon_sender.send(event).await;
+    }
+    if let Some(inverted) = outputs.inverted {
+        tank_sender.send(TankDriveEvent::SetInverted(inverted)).await;
+    }
+    if let Some(event) = outputs.tank {
+        tank_sender.send(event).await;
+    }
+    if let Some(event) = outputs.servo {
+        servo_sender.send(event).await;
+    }
+    if let Some(event) = outputs.led {
+        led_sender.send(event).await;
+    }
+}
+
+/// Runs the state machine at exactly the configured loop rate
+///
+/// Each tick samples the newest controller frame from the mailbox, checks
+/// fault sources and forwards the resulting events. Ticks
+/// whose work takes longer than the loop period are counted as overruns.
+#[embassy_executor::task]
+pub async fn state_controller_task(
+    mut controller_receiver: watch::Receiver<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    mut battery_receiver: watch::Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    mut orientation_receiver: watch::Receiver<'static, CriticalSectionRawMutex, Orientation, 2>,
+    mut settings_receiver: watch::Receiver<'static, CriticalSectionRawMutex, Settings, 2>,
+    tank_sender: Sender<'static, CriticalSectionRawMutex, TankDriveEvent, 8>,
+    servo_sender: Sender<'static, CriticalSectionRawMutex, ServoEvent, 8>,
+    weapon_sender: Sender<'static, CriticalSectionRawMutex, WeaponEvent, 8>,
+    led_sender: Sender<'static, CriticalSectionRawMutex, LedEvent, 8>,
+    status_sender: watch::Sender<'static, CriticalSectionRawMutex, Status, 5>,
+    emergency_signal: &'static Signal<CriticalSectionRawMutex, EmergencyReason>,
+    boot_emergency_signal: &'static Signal<CriticalSectionRawMutex, EmergencyReason>,
+    loop_stats_sender: watch::Sender<'static, CriticalSectionRawMutex, LoopStats, 1>,
+    motor_test_signal: &'static Signal<CriticalSectionRawMutex, MotorTest>,
+) {
+    // Core 0 publishes these once it has read flash
+    let mut settings = settings_receiver.get().await;
+    info!("State controller starting at {} Hz...", settings.control_loop_hz);
+
+    let mut state_machine = StateMachine::new();
+    state_machine.set_settings(settings);
+    let mut heartbeat = HeartbeatMonitor::new(&INPUT_HEARTBEAT);
+    let mut latest: Option<ControllerData> = None;
+    let mut samples = SampleTracker::new();
+    let mut stats = LoopStats::new();
+    let mut flip = FlipDetector::new(FLIP_THRESHOLD_MG, FLIP_DEBOUNCE_MS);
+    let mut ticker = Ticker::every(Duration::from_hz(settings.control_loop_hz as u64));
+
+    loop {
+        ticker.next().await;
+        let tick_start = Instant::now();
+        check_in(WatchedTask::StateController);
+        ACTUATION_HEARTBEAT.beat();
+
+        if let Some(new) = settings_receiver.try_changed() {
+            if new.control_loop_hz != settings.control_loop_hz {
+                info!("Control loop rate now {} Hz", new.control_loop_hz);
+                ticker = Ticker::every(Duration::from_hz(new.control_loop_hz as u64));
+            }
+            settings = new;
+            state_machine.set_settings(settings);
+        }
+
+        // Faults on the input core are checked every tick, with or without data.
+        // A quiet input core is treated like a lost link, so it gets the same
+        // grace period before escalating.
+        if panicked_core().is_some() {
+            let outputs = state_machine.trigger_emergency(EmergencyReason::CorePanic);
+            send_outputs(outputs, &tank_sender, &servo_sender, &weapon_sender, &led_sender).await;
+        }
+        state_machine.set_input_alive(heartbeat.is_alive());
+        // The boot reason goes first: the first emergency is the one kept
+        if let Some(reason) = boot_emergency_signal.try_take() {
+            let outputs = state_machine.trigger_emergency(reason);
+            send_outputs(outputs, &tank_sender, &servo_sender, &weapon_sender, &led_sender).await;
+        }
+        if let Some(reason) = emergency_signal.try_take() {
+            let outputs = state_machine.trigger_emergency(reason);
+            send_outputs(outputs, &tank_sender, &servo_sender, &weapon_sender, &led_sender).await;
+        }
+
+        // Runs from the next update, and only if the bot is still idle
+        if let Some(test) = motor_test_signal.try_take() {
+            let (left, right) = test.sides();
+            state_machine.start_motor_test(left, right, test.duration_ms);
+        }
+
+        // Critical is handled by the battery task raising an emergency
+        if let Some(battery) = battery_receiver.try_changed() {
+            state_machine.set_battery_low(battery.level.is_low());
+        }
+
+        if let Some(orientation) = orientation_receiver.try_changed() {
+            if let Some(flipped) = flip.update(orientation.accel_mg[2], tick_start.as_millis()) {
+                state_machine.set_flipped(flipped);
+            }
+        }
+
+        // Latest-value-wins: staleness is judged by the frame timestamp
+        if let Some(frame) = controller_receiver.try_changed() {
+            let skipped = samples.observe(frame.sequence);
+            if skipped > 0 {
+                trace!("Skipped {} controller samples ({} total)", skipped, samples.dropped);
+            }
+            latest = Some(frame);
+        }
+
+        let outputs = state_machine.update(latest.as_ref(), tick_start.as_millis());
+        send_outputs(outputs, &tank_sender, &servo_sender, &weapon_sender, &led_sender).await;
+
+        let status = state_machine.status();
+        if status_sender.try_get() != Some(status) {
+            status_sender.send(status);
+        }
+
+        let elapsed_us = tick_start.elapsed().as_micros() as u32;
+        let period_us = settings.control_loop_period_us();
+        if stats.record(elapsed_us, period_us) {
+            warn!(
+                "Control loop overrun: {} us > {} us ({} of {} ticks)",
+                elapsed_us, period_us, stats.overruns, stats.iterations
+            );
+        }
+        loop_stats_sender.send(stats);
+    }
+}
+
+#[embassy_executor::task]
+pub async fn tank_driver_task(
+    motor_peripherals: PeripheralsMotor,
+    tank_receiver: Receiver<'static, CriticalSectionRawMutex, TankDriveEvent, 8>,
+    mut current_receiver: watch::Receiver<'static, CriticalSectionRawMutex, DriveCurrent, 3>,
+    mut orientation_receiver: watch::Receiver<'static, CriticalSectionRawMutex, Orientation, 2>,
+    drive_sender: watch::Sender<'static, CriticalSectionRawMutex, DriveOutput, 1>,
+) {
+    info!("Tank driver task starting...");
+
+    let mut tank_drive = new_tank_drive(motor_peripherals);
+    tank_drive.set_slew_rate(DRIVE_SLEW_RATE);
+    tank_drive.set_heading_hold(HEADING_HOLD);
+
+    // Ramps advance on this ticker whether or not new events arrive
+    let mut ramp_ticker = Ticker::every(Duration::from_millis(DRIVE_RAMP_TICK_MS));
+
+    loop {
+        let event = match select(tank_receiver.receive(), ramp_ticker.next()).await {
+            Either::First(event) => event,
+            Either::Second(()) => {
+                check_in(WatchedTask::TankDriver);
+
+                // Over-current derating is picked up with the ramps
+                if let Some(current) = current_receiver.try_changed() {
+                    tank_drive.set_duty_limit(current.left.duty_limit, current.right.duty_limit);
+                }
+                if let Some(orientation) = orientation_receiver.try_changed() {
+                    tank_drive.set_yaw_rate(orientation.yaw_rate_mdps);
+                }
+                if let Err(e) = tank_drive.tick(DRIVE_RAMP_TICK_MS as u32) {
+                    warn!("Tank drive ramp failed: {}", e);
+                }
+                let (left, right) = tank_drive.speeds();
+                let output = DriveOutput { left, right };
+                if drive_sender.try_get() != Some(output) {
+                    drive_sender.send(output);
+                }
+                continue;
+            }
+        };
+
+        let result = match event {
+            TankDriveEvent::Move { x, y } => tank_drive.drive(x, y),
+            TankDriveEvent::Spin(speed) => tank_drive.spin(speed),
+            TankDriveEvent::Stop => tank_drive.stop(),
+            TankDriveEvent::Sides { left, right } => tank_drive.drive_sides(left, right),
+            TankDriveEvent::Enable => tank_drive.enable(),
+            TankDriveEvent::Disable => tank_drive.disable(),
+            TankDriveEvent::SetInverted(inverted) => {
+                tank_drive.set_inverted(inverted);
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            warn!("Tank drive {} failed: {}", event, e);
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn servo_driver_task(
+    mut servo: ServoController,
+    servo_receiver: Receiver<'static, CriticalSectionRawMutex, ServoEvent, 8>,
+) {
+    info!("Servo driver task starting...");
+
+    loop {
+        let event = servo_receiver.receive().await;
+
+        match event {
+            ServoEvent::SetAngle(angle) => {
+                servo.set_angle(angle);
+            }
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn weapon_driver_task(
+    mut weapon: Weapon,
+    weapon_receiver: Receiver<'static, CriticalSectionRawMutex, WeaponEvent, 8>,
+) {
+    info!("Weapon driver task starting...");
+
+    let mut ramp_ticker = Ticker::every(Duration::from_millis(WEAPON_RAMP_TICK_MS));
+
+    loop {
+        let event = match select(weapon_receiver.receive(), ramp_ticker.next()).await {
+            Either::First(event) => event,
+            Either::Second(()) => {
+                if let Err(e) = weapon.tick(WEAPON_RAMP_TICK_MS as u32) {
+                    warn!("Weapon ramp failed: {}", e);
+                }
+                continue;
+            }
+        };
+
+        let result = match event {
+            WeaponEvent::Spin(speed) => weapon.set_speed(speed),
+            WeaponEvent::Stop => weapon.stop(),
+            WeaponEvent::Kill => weapon.kill(),
+        };
+
+        if let Err(e) = result {
+            warn!("Weapon {} failed: {}", event, e);
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn led_driver_task(
+    led_peripherals: PeripheralsStateLed,
+    led_receiver: Receiver<'static, CriticalSectionRawMutex, LedEvent, 8>,
+) {
+    info!("LED driver task starting...");
+
+    let mut led = Output::new(led_peripherals.PIN_25, Level::Low);
+    let mut current_pattern = LedEvent::Off;
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    let mut blink_step: u16 = 0;
+
+    loop {
+        // Check for new LED pattern
+        if let Ok(event) = led_receiver.try_receive() {
+            current_pattern = event;
+            blink_step = 0;
+            ticker = match event {
+                LedEvent::SlowBlink => Ticker::every(Duration::from_millis(500)),
+                LedEvent::FastBlink => Ticker::every(Duration::from_millis(100)),
+                LedEvent::BlinkCode(_) | LedEvent::Warning | LedEvent::Inverted | LedEvent::Countdown(_) => {
+                    Ticker::every(Duration::from_millis(BLINK_CODE_TICK_MS))
+                }
+                _ => Ticker::every(Duration::from_millis(100)),
+            };
+        }
+
+        // Execute current pattern
+        match current_pattern {
+            LedEvent::Off => {
+                led.set_low();
+                ticker.next().await;
+            }
+            LedEvent::Solid => {
+                led.set_high();
+                ticker.next().await;
+            }
+            LedEvent::SlowBlink | LedEvent::FastBlink => {
+                led.toggle();
+                ticker.next().await;
+            }
+            LedEvent::BlinkCode(count) => {
+                // `count` flashes (one on tick, one off tick each), then a pause
+                let flash_ticks = count as u16 * 2;
+                if blink_step < flash_ticks && blink_step % 2 == 0 {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+                blink_step = (blink_step + 1) % (flash_ticks + BLINK_CODE_PAUSE_TICKS);
+                ticker.next().await;
+            }
+            LedEvent::Warning => {
+                // Three ticks on, one off
+                if blink_step < 3 {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+                blink_step = (blink_step + 1) % 4;
+                ticker.next().await;
+            }
+            LedEvent::Inverted => {
+                // Two flashes, then a pause
+                if blink_step == 0 || blink_step == 2 {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+                blink_step = (blink_step + 1) % 8;
+                ticker.next().await;
+            }
+            LedEvent::Countdown(_) => {
+                // A new event arrives every second, each starting with a flash
+                if blink_step == 0 {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+                blink_step = blink_step.saturating_add(1);
+                ticker.next().await;
+            }
+        }
+    }
+}
+
@@ -0,0 +1,32 @@
+//! Last-resort output shutdown for the panic handler
+//!
+//! The drivers own their pins and PWM slices, and a panicking core cannot
+//! borrow them back, so this goes straight to the registers. It only ever
+//! turns things off, which is safe to race against the other core.
+
+use embassy_rp::pac;
+
+/// TB6612 standby pins, held low to disable the H-bridges
+#[cfg(not(feature = "four-motor"))]
+const STANDBY_PINS: u32 = 1 << 19;
+#[cfg(feature = "four-motor")]
+const STANDBY_PINS: u32 = (1 << 19) | (1 << 5);
+
+/// Drive (0, 3), and weapon ESC and servo (2) slices
+const OUTPUT_SLICES: [usize; 3] = [0, 2, 3];
+
+/// De-energize every actuator: drivers into standby and all duties to zero
+///
+/// With no pulses the weapon ESC and servo fall back to their own signal
+/// loss behaviour, which for the ESC is off.
+pub fn force_safe_outputs() {
+    pac::SIO.gpio_out_clr().write_value(STANDBY_PINS);
+    pac::SIO.gpio_oe_set().write_value(STANDBY_PINS);
+
+    for slice in OUTPUT_SLICES {
+        pac::PWM.ch(slice).cc().write(|w| {
+            w.set_a(0);
+            w.set_b(0);
+        });
+    }
+}
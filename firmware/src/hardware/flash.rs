@@ -0,0 +1,12 @@
+use embassy_rp::flash::{Blocking, Flash};
+use embassy_rp::peripherals::FLASH;
+use rip_core::config::FLASH_SIZE;
+
+use super::PeripheralsFlash;
+
+/// Whole QSPI flash, shared by the settings sector and the blackbox region
+pub type SystemFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+
+pub fn new_flash(p: PeripheralsFlash) -> SystemFlash {
+    Flash::new_blocking(p.FLASH)
+}
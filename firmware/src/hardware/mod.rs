@@ -0,0 +1,28 @@
+//! Hardware abstraction layer for robot components
+
+pub mod failsafe;
+pub mod flash;
+pub mod motor_controller;
+pub mod peripherals;
+#[cfg(feature = "rc-pulse")]
+pub mod pulse_capture;
+#[cfg(feature = "rc-serial")]
+pub mod rc_uart;
+pub mod servo_controller;
+pub mod tank_drive_controller;
+pub mod usb;
+pub mod weapon_controller;
+
+pub use failsafe::force_safe_outputs;
+pub use flash::{new_flash, SystemFlash};
+pub use peripherals::{split_peripherals, Peripherals0, Peripherals1};
+pub use peripherals::{PeripheralsController, PeripheralsFlash, PeripheralsPs2Led, PeripheralsStateLed, PeripheralsUsb, PeripheralsWatchdog};
+pub use peripherals::{PeripheralsAnalog, PeripheralsImu, PeripheralsMotor, PeripheralsWeapon};
+#[cfg(feature = "rc-pulse")]
+pub use pulse_capture::PulseCapture;
+#[cfg(feature = "rc-serial")]
+pub use rc_uart::{new_rc_uart, RcUart};
+pub use servo_controller::ServoController;
+pub use tank_drive_controller::{new_tank_drive, TankDrive};
+pub use usb::{new_usb, SerialClass, UsbDevice};
+pub use weapon_controller::{new_weapon_and_servo, Weapon};
\ No newline at end of file
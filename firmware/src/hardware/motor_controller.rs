@@ -1,15 +1,18 @@
 use defmt::*;
 use embassy_rp::gpio::{Level, Output};
 use embassy_rp::peripherals::{PIN_16, PIN_17, PIN_18, PIN_19, PWM_SLICE0};
-use embassy_rp::pwm::{Config as PwmConfig, Pwm};
-use tb6612fng::{DriveCommand, Motor};
+use embassy_rp::pwm::Pwm;
+use rip_core::motor::{MotorDriver, MotorError, Tb6612Motor};
 
-pub struct MotorController {
-    motor: Motor<Output<'static>, Output<'static>, Pwm<'static>>,
+use super::tank_drive_controller::{motor_pwm_config, Tb6612};
+
+/// Single motor driven straight from a stick, generic over the driver chip
+pub struct MotorController<M> {
+    motor: M,
     standby: Output<'static>,
 }
 
-impl MotorController {
+impl MotorController<Tb6612> {
     pub fn new(
         pwm: PWM_SLICE0,
         pwm_pin: PIN_16,
@@ -18,74 +21,72 @@ impl MotorController {
         standby_pin: PIN_19,
     ) -> Self {
         // Configure PWM for motor speed control
-        let mut pwm_config = PwmConfig::default();
-        pwm_config.divider = 125.into(); // For 1MHz counting frequency
-        pwm_config.top = 100; // For 10kHz PWM frequency (1MHz / 100)
-        pwm_config.compare_a = 0; // Start with motor stopped
-
-        let pwm = Pwm::new_output_a(pwm, pwm_pin, pwm_config);
+        let (pwm, _) = Pwm::new_output_a(pwm, pwm_pin, motor_pwm_config()).split();
 
         // Configure direction pins
         let in1 = Output::new(in1_pin, Level::Low);
         let in2 = Output::new(in2_pin, Level::Low);
 
+        let motor = Tb6612Motor::new(in1, in2, pwm.unwrap()).unwrap();
+        Self::with_driver(motor, standby_pin)
+    }
+}
+
+impl<M: MotorDriver> MotorController<M> {
+    pub fn with_driver(motor: M, standby_pin: PIN_19) -> Self {
         // Configure standby pin (active high to enable motor)
         let mut standby = Output::new(standby_pin, Level::Low);
         standby.set_high(); // Enable the motor driver
 
-        let motor = Motor::new(in1, in2, pwm).unwrap();
-
         MotorController { motor, standby }
     }
 
-    pub fn control_from_stick(&mut self, stick_y: u8) {
+    pub fn control_from_stick(&mut self, stick_y: u8) -> Result<(), MotorError> {
         // PS2 stick values: 0-255, with 128 being center
         // Convert to motor control with dead zone
 
         if stick_y < 118 {
             // Forward (stick pushed up)
             // Map 0-117 to 100-0% speed
-            let speed = ((118 - stick_y) as f32 / 118.0 * 100.0) as u8;
+            let speed = ((118 - stick_y) as f32 / 118.0 * 100.0) as i8;
             info!("Stick Y: {} => Motor forward at {}%", stick_y, speed);
-            self.motor.drive(DriveCommand::Forward(speed)).unwrap();
+            self.motor.set_speed(speed)
         } else if stick_y > 138 {
             // Backward (stick pushed down)
             // Map 139-255 to 0-100% speed
-            let speed = ((stick_y - 138) as f32 / 117.0 * 100.0) as u8;
+            let speed = ((stick_y - 138) as f32 / 117.0 * 100.0) as i8;
             info!("Stick Y: {} => Motor backward at {}%", stick_y, speed);
-            self.motor.drive(DriveCommand::Backward(speed)).unwrap();
+            self.motor.set_speed(-speed)
         } else {
             // Dead zone (118-138)
-            self.motor.drive(DriveCommand::Stop).unwrap();
+            self.motor.coast()
         }
     }
 
-    pub fn stop(&mut self) {
-        self.motor.drive(DriveCommand::Stop).unwrap();
+    pub fn stop(&mut self) -> Result<(), MotorError> {
+        self.motor.coast()
     }
 
-    pub fn brake(&mut self) {
-        self.motor.drive(DriveCommand::Brake).unwrap();
+    pub fn brake(&mut self) -> Result<(), MotorError> {
+        self.motor.brake()
     }
 
-    pub fn disable(&mut self) {
-        self.stop(); // Stop motor before disabling
+    pub fn disable(&mut self) -> Result<(), MotorError> {
+        self.motor.set_enabled(false)?; // Stop motor before disabling
         self.standby.set_low();
+        Ok(())
     }
 
-    pub fn enable(&mut self) {
+    pub fn enable(&mut self) -> Result<(), MotorError> {
         self.standby.set_high();
+        self.motor.set_enabled(true)
     }
 
-    pub fn current_drive_command(&self) -> DriveCommand {
-        *self.motor.current_drive_command()
-    }
-
-    pub fn drive_forward(&mut self, speed: u8) {
-        self.motor.drive(DriveCommand::Forward(speed)).unwrap();
+    pub fn drive_forward(&mut self, speed: u8) -> Result<(), MotorError> {
+        self.motor.set_speed(speed.min(100) as i8)
     }
 
-    pub fn drive_backward(&mut self, speed: u8) {
-        self.motor.drive(DriveCommand::Backward(speed)).unwrap();
+    pub fn drive_backward(&mut self, speed: u8) -> Result<(), MotorError> {
+        self.motor.set_speed(-(speed.min(100) as i8))
     }
 }
@@ -31,52 +31,103 @@ macro_rules! make_peripherals {
     };
 }
 
+#[cfg(not(any(feature = "rc-serial", feature = "rc-pulse")))]
 make_peripherals! {
     PeripheralsController,
     (SPI1, PIN_12, PIN_13, PIN_14, PIN_15)  // PS2 controller SPI
 }
 
+#[cfg(feature = "rc-serial")]
+make_peripherals! {
+    PeripheralsController,
+    (UART0, PIN_13)  // RC receiver on UART0 RX, in place of the PS2 port
+}
+
+#[cfg(feature = "rc-ppm")]
+make_peripherals! {
+    PeripheralsController,
+    (PIO0, PIN_13)  // PPM receiver captured by PIO0, in place of the PS2 port
+}
+
+#[cfg(feature = "rc-pwm")]
+make_peripherals! {
+    PeripheralsController,
+    (PIO0, PIN_12, PIN_13, PIN_14, PIN_15)  // PWM receiver channels captured by PIO0
+}
+
 make_peripherals! {
     PeripheralsPs2Led,
     (PIN_22)  // PS2 connection status LED (Core 0)
 }
 
+make_peripherals! {
+    PeripheralsWatchdog,
+    (WATCHDOG)  // Hardware watchdog, fed from Core 0
+}
+
+make_peripherals! {
+    PeripheralsFlash,
+    (FLASH)  // QSPI flash, for the blackbox log (Core 0)
+}
+
+make_peripherals! {
+    PeripheralsUsb,
+    (USB)  // USB serial console (Core 0)
+}
+
 make_peripherals! {
     PeripheralsStateLed,
     (PIN_25)  // Bot state LED (Core 1)
 }
 
+#[cfg(not(feature = "four-motor"))]
 make_peripherals! {
     PeripheralsMotor,
     (PWM_SLICE0, PWM_SLICE3, PIN_16, PIN_17, PIN_18, PIN_19, PIN_7, PIN_8, PIN_9)  // Dual motor drivers (BR and FL)
 }
 
+#[cfg(feature = "four-motor")]
 make_peripherals! {
-    PeripheralsServo,
-    (PWM_SLICE5, PIN_26)  // Servo control
+    PeripheralsMotor,
+    (
+        PWM_SLICE0, PWM_SLICE3,
+        PIN_16, PIN_2, PIN_18,   // BR: PWM, IN1, IN2 (driver A)
+        PIN_17, PIN_3, PIN_4,    // FR: PWM, IN1, IN2 (driver A)
+        PIN_7, PIN_8, PIN_9,     // FL: PWM, IN1, IN2 (driver B)
+        PIN_6, PIN_10, PIN_11,   // BL: PWM, IN1, IN2 (driver B)
+        PIN_19, PIN_5            // STBY for driver A and B
+    )  // Quad motor drivers on both channels of slices 0 and 3
 }
 
 make_peripherals! {
     PeripheralsWeapon,
-    (PWM_SLICE1, PWM_SLICE2, PIN_20, PIN_21)  // Future weapon systems
+    (PWM_SLICE1, PWM_SLICE2, PIN_20, PIN_21)  // Spinner ESC on PIN_20 and servo on PIN_21 (slice 2), slice 1 reserved
+}
+
+make_peripherals! {
+    PeripheralsImu,
+    (I2C0, PIN_0, PIN_1)  // MPU-6050 IMU (SDA, SCL)
 }
 
 make_peripherals! {
-    PeripheralsSensors,
-    (I2C0, PIN_0, PIN_1, ADC, PIN_27, PIN_28)  // Future sensors (IMU, current sensing, etc.)
+    PeripheralsAnalog,
+    (ADC, PIN_26, PIN_27, PIN_28)  // Right current (PIN_26), left current (PIN_27), battery voltage (PIN_28)
 }
 
 pub struct Peripherals0 {
     pub controller: PeripheralsController,
     pub ps2_led: PeripheralsPs2Led,
+    pub watchdog: PeripheralsWatchdog,
+    pub flash: PeripheralsFlash,
+    pub usb: PeripheralsUsb,
 }
 
 pub struct Peripherals1 {
     pub motor: PeripheralsMotor,
-    pub servo: PeripheralsServo,
     pub state_led: PeripheralsStateLed,
     pub weapon: PeripheralsWeapon,
-    pub sensors: PeripheralsSensors,
+    pub imu: PeripheralsImu,
+    pub analog: PeripheralsAnalog,
 }
 
 pub fn split_peripherals(p: Peripherals) -> (CORE1, Peripherals0, Peripherals1) {
@@ -85,13 +136,16 @@ pub fn split_peripherals(p: Peripherals) -> (CORE1, Peripherals0, Peripherals1)
         Peripherals0 {
             controller: peripherals_controller!(p),
             ps2_led: peripherals_ps2_led!(p),
+            watchdog: peripherals_watchdog!(p),
+            flash: peripherals_flash!(p),
+            usb: peripherals_usb!(p),
         },
         Peripherals1 {
             motor: peripherals_motor!(p),
-            servo: peripherals_servo!(p),
             state_led: peripherals_state_led!(p),
             weapon: peripherals_weapon!(p),
-            sensors: peripherals_sensors!(p),
+            imu: peripherals_imu!(p),
+            analog: peripherals_analog!(p),
         },
     )
 }
@@ -0,0 +1,146 @@
+//! Pulse timing on PIO0
+//!
+//! Each state machine counts in microseconds while its pin is in the state
+//! being timed, and pushes the count into its RX FIFO. PWM channels get one
+//! state machine each, timing the high pulse. PPM gets one timing rising
+//! edge to rising edge, which is the channel interval whichever way up the
+//! receiver drives the line.
+
+use embassy_rp::bind_interrupts;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::peripherals::PIO0;
+use embassy_rp::pio::{Common, Config, FifoJoin, InterruptHandler, LoadedProgram, Pin, Pio, StateMachine};
+use fixed::types::U24F8;
+
+use super::PeripheralsController;
+
+bind_interrupts!(struct Irqs {
+    PIO0_IRQ_0 => InterruptHandler<PIO0>;
+});
+
+/// Every counting loop below takes three cycles, so this gives 1 µs counts
+const COUNT_HZ: f32 = 3_000_000.0;
+
+fn capture_config<'d>(program: &LoadedProgram<'d, PIO0>, pin: &Pin<'d, PIO0>) -> Config<'d, PIO0> {
+    let mut cfg = Config::default();
+    cfg.use_program(program, &[]);
+    cfg.set_in_pins(&[pin]);
+    cfg.set_jmp_pin(pin);
+    cfg.fifo_join = FifoJoin::RxOnly;
+    cfg.clock_divider = U24F8::from_num(clk_sys_freq() as f32 / COUNT_HZ);
+    cfg
+}
+
+/// PPM frame interval capture on GPIO13
+#[cfg(feature = "rc-ppm")]
+pub struct PulseCapture {
+    sm: StateMachine<'static, PIO0, 0>,
+    // Held so the program memory and pin stay claimed
+    _common: Common<'static, PIO0>,
+    _pin: Pin<'static, PIO0>,
+}
+
+#[cfg(feature = "rc-ppm")]
+impl PulseCapture {
+    pub fn new(p: PeripheralsController) -> Self {
+        let Pio { mut common, mut sm0, .. } = Pio::new(p.PIO0, Irqs);
+
+        // Rising edge to rising edge: count through the high part, then the
+        // low part, three cycles per count in both
+        let program = pio_proc::pio_asm!(
+            "    wait 0 pin 0",
+            "    wait 1 pin 0",
+            ".wrap_target",
+            "    mov x, ~null",
+            "high:",
+            "    jmp x-- high_next",
+            "high_next:",
+            "    jmp pin high [1]",
+            "low:",
+            "    jmp x-- low_next",
+            "low_next:",
+            "    jmp pin done",
+            "    jmp low",
+            "done:",
+            "    mov isr, ~x",
+            "    push noblock",
+            ".wrap",
+        );
+
+        let loaded = common.load_program(&program.program);
+        let pin = common.make_pio_pin(p.PIN_13);
+        sm0.set_config(&capture_config(&loaded, &pin));
+        sm0.set_enable(true);
+
+        PulseCapture { sm: sm0, _common: common, _pin: pin }
+    }
+
+    /// Wait for the next interval, in microseconds
+    pub async fn next_interval(&mut self) -> u32 {
+        self.sm.rx().wait_pull().await
+    }
+}
+
+/// PWM pulse capture on GPIO12 to GPIO15, one state machine per pin
+#[cfg(feature = "rc-pwm")]
+pub struct PulseCapture {
+    sm0: StateMachine<'static, PIO0, 0>,
+    sm1: StateMachine<'static, PIO0, 1>,
+    sm2: StateMachine<'static, PIO0, 2>,
+    sm3: StateMachine<'static, PIO0, 3>,
+    // Held so the program memory and pins stay claimed
+    _common: Common<'static, PIO0>,
+    _pins: [Pin<'static, PIO0>; 4],
+}
+
+#[cfg(feature = "rc-pwm")]
+impl PulseCapture {
+    pub fn new(p: PeripheralsController) -> Self {
+        let Pio { mut common, mut sm0, mut sm1, mut sm2, mut sm3, .. } = Pio::new(p.PIO0, Irqs);
+
+        // Time the high pulse, three cycles per count
+        let program = pio_proc::pio_asm!(
+            ".wrap_target",
+            "    mov x, ~null",
+            "    wait 0 pin 0",
+            "    wait 1 pin 0",
+            "high:",
+            "    jmp x-- high_next",
+            "high_next:",
+            "    jmp pin high [1]",
+            "    mov isr, ~x",
+            "    push noblock",
+            ".wrap",
+        );
+
+        let pins = [
+            common.make_pio_pin(p.PIN_12),
+            common.make_pio_pin(p.PIN_13),
+            common.make_pio_pin(p.PIN_14),
+            common.make_pio_pin(p.PIN_15),
+        ];
+        // All four share one copy of the program
+        let loaded = common.load_program(&program.program);
+        sm0.set_config(&capture_config(&loaded, &pins[0]));
+        sm1.set_config(&capture_config(&loaded, &pins[1]));
+        sm2.set_config(&capture_config(&loaded, &pins[2]));
+        sm3.set_config(&capture_config(&loaded, &pins[3]));
+        sm0.set_enable(true);
+        sm1.set_enable(true);
+        sm2.set_enable(true);
+        sm3.set_enable(true);
+
+        PulseCapture { sm0, sm1, sm2, sm3, _common: common, _pins: pins }
+    }
+
+    /// The oldest unread pulse width on capture pin `pin`, in microseconds
+    pub fn try_pulse(&mut self, pin: usize) -> Option<u32> {
+        match pin {
+            0 => self.sm0.rx().try_pull(),
+            1 => self.sm1.rx().try_pull(),
+            2 => self.sm2.rx().try_pull(),
+            3 => self.sm3.rx().try_pull(),
+            _ => None,
+        }
+    }
+}
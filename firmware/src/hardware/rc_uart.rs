@@ -0,0 +1,36 @@
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::UART0;
+use embassy_rp::uart::{self, BufferedInterruptHandler, BufferedUartRx};
+use static_cell::StaticCell;
+
+use super::PeripheralsController;
+
+bind_interrupts!(struct Irqs {
+    UART0_IRQ => BufferedInterruptHandler<UART0>;
+});
+
+pub type RcUart = BufferedUartRx<'static, UART0>;
+
+/// A few frames of either protocol, so a slow poll never drops bytes
+static RX_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
+
+/// Receive-only UART for the RC receiver, set up for whichever protocol
+/// the firmware was built for
+pub fn new_rc_uart(p: PeripheralsController) -> RcUart {
+    let mut config = uart::Config::default();
+    #[cfg(feature = "rc-sbus")]
+    {
+        // SBUS is inverted RS-232 style signalling at an odd baud rate
+        config.baudrate = 100_000;
+        config.data_bits = uart::DataBits::DataBits8;
+        config.parity = uart::Parity::ParityEven;
+        config.stop_bits = uart::StopBits::STOP2;
+        config.invert_rx = true;
+    }
+    #[cfg(feature = "rc-crsf")]
+    {
+        config.baudrate = 420_000;
+    }
+
+    BufferedUartRx::new(p.UART0, Irqs, p.PIN_13, RX_BUFFER.init([0; 256]), config)
+}
@@ -0,0 +1,28 @@
+use embassy_rp::pwm::{PwmOutput, SetDutyCycle};
+use rip_core::motor::{RC_PULSE_MAX_US, RC_PULSE_MIN_US};
+
+/// Hobby servo on one output of a PWM slice counting in microseconds
+pub struct ServoController {
+    pwm: PwmOutput<'static>,
+}
+
+impl ServoController {
+    /// `pwm` must count at 1MHz with an RC frame period; see
+    /// `new_weapon_and_servo`
+    pub fn new(pwm: PwmOutput<'static>) -> Self {
+        ServoController { pwm }
+    }
+
+    /// 0 to 180 degrees as a 1ms to 2ms pulse, whatever the frame length
+    pub fn set_angle(&mut self, angle: u8) {
+        let range_us = RC_PULSE_MAX_US - RC_PULSE_MIN_US;
+        let pulse_us = RC_PULSE_MIN_US + range_us * angle.min(180) as u32 / 180;
+
+        self.pwm.set_duty_cycle(pulse_us as u16).unwrap();
+    }
+
+    pub fn control_from_stick(&mut self, stick_x: u8) {
+        let angle = (stick_x as u32 * 180) / 255;
+        self.set_angle(angle as u8);
+    }
+}
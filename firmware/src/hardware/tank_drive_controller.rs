@@ -0,0 +1,104 @@
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::pwm::{Config as PwmConfig, Pwm, PwmOutput};
+use rip_core::config::*;
+use rip_core::motor::{CalibratedMotor, MotorCalibration, Tb6612Motor};
+use rip_core::tank_drive::TankDriveController;
+#[cfg(feature = "four-motor")]
+use rip_core::{motor::MotorPair, tank_drive::StandbyPair};
+
+use super::PeripheralsMotor;
+
+/// One TB6612FNG channel on RP2040 pins
+pub type Tb6612 = Tb6612Motor<Output<'static>, Output<'static>, PwmOutput<'static>>;
+
+/// TB6612FNG channel with its per-motor calibration applied
+pub type DriveMotor = CalibratedMotor<Tb6612>;
+
+/// Tank drive as wired on the bot: two TB6612FNG channels sharing STBY
+#[cfg(not(feature = "four-motor"))]
+pub type TankDrive = TankDriveController<DriveMotor, Output<'static>>;
+
+/// Tank drive as wired on the bot: a front/back pair per side, one
+/// TB6612FNG per side with its own STBY
+#[cfg(feature = "four-motor")]
+pub type TankDrive = TankDriveController<MotorPair<DriveMotor>, StandbyPair<Output<'static>, Output<'static>>>;
+
+/// PWM for motor speed control
+pub fn motor_pwm_config() -> PwmConfig {
+    let mut pwm_config = PwmConfig::default();
+    pwm_config.divider = 125.into(); // For 1MHz counting frequency
+    pwm_config.top = 100; // For 10kHz PWM frequency (1MHz / 100)
+    pwm_config.compare_a = 0; // Start with motors stopped
+    pwm_config.compare_b = 0;
+    pwm_config
+}
+
+/// Build one calibrated TB6612FNG channel from its PWM output and direction pins
+fn tb6612(
+    pwm: Option<PwmOutput<'static>>,
+    in1: Output<'static>,
+    in2: Output<'static>,
+    calibration: MotorCalibration,
+) -> DriveMotor {
+    CalibratedMotor::new(Tb6612Motor::new(in1, in2, pwm.unwrap()).unwrap(), calibration)
+}
+
+/// Build the tank drive for opposite corner motors
+/// BR motor: controls right side thrust
+/// FL motor: controls left side thrust
+#[cfg(not(feature = "four-motor"))]
+pub fn new_tank_drive(p: PeripheralsMotor) -> TankDrive {
+    let pwm_config = motor_pwm_config();
+
+    // Back Right motor setup
+    let (pwm_br, _) = Pwm::new_output_a(p.PWM_SLICE0, p.PIN_16, pwm_config.clone()).split();
+    let motor_br = tb6612(
+        pwm_br,
+        Output::new(p.PIN_17, Level::Low),
+        Output::new(p.PIN_18, Level::Low),
+        MOTOR_CAL_BR,
+    );
+
+    // Front Left motor setup (direction flipped by its calibration)
+    let (_, pwm_fl) = Pwm::new_output_b(p.PWM_SLICE3, p.PIN_7, pwm_config).split();
+    let motor_fl = tb6612(
+        pwm_fl,
+        Output::new(p.PIN_8, Level::Low),
+        Output::new(p.PIN_9, Level::Low),
+        MOTOR_CAL_FL,
+    );
+
+    // Standby pin (active high), raised by the controller to enable the driver
+    let standby = Output::new(p.PIN_19, Level::Low);
+
+    TankDriveController::new(motor_fl, motor_br, standby).unwrap()
+}
+
+/// Build the tank drive for all four wheels
+/// Driver A (slice 0, STBY PIN_19): right side, BR on channel A, FR on B
+/// Driver B (slice 3, STBY PIN_5): left side, BL on channel A, FL on B
+#[cfg(feature = "four-motor")]
+pub fn new_tank_drive(p: PeripheralsMotor) -> TankDrive {
+    let pwm_config = motor_pwm_config();
+
+    let (pwm_br, pwm_fr) =
+        Pwm::new_output_ab(p.PWM_SLICE0, p.PIN_16, p.PIN_17, pwm_config.clone()).split();
+    let (pwm_bl, pwm_fl) = Pwm::new_output_ab(p.PWM_SLICE3, p.PIN_6, p.PIN_7, pwm_config).split();
+
+    let motor_br = tb6612(pwm_br, Output::new(p.PIN_2, Level::Low), Output::new(p.PIN_18, Level::Low), MOTOR_CAL_BR);
+    let motor_fr = tb6612(pwm_fr, Output::new(p.PIN_3, Level::Low), Output::new(p.PIN_4, Level::Low), MOTOR_CAL_FR);
+    let motor_fl = tb6612(pwm_fl, Output::new(p.PIN_8, Level::Low), Output::new(p.PIN_9, Level::Low), MOTOR_CAL_FL);
+    let motor_bl = tb6612(pwm_bl, Output::new(p.PIN_10, Level::Low), Output::new(p.PIN_11, Level::Low), MOTOR_CAL_BL);
+
+    let standby = StandbyPair(
+        Output::new(p.PIN_19, Level::Low),
+        Output::new(p.PIN_5, Level::Low),
+    );
+
+    TankDriveController::new(
+        MotorPair::new(motor_fl, motor_bl),
+        MotorPair::new(motor_fr, motor_br),
+        standby,
+    )
+    .unwrap()
+}
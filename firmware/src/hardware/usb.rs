@@ -0,0 +1,58 @@
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::{Builder, Config};
+use static_cell::StaticCell;
+
+use super::PeripheralsUsb;
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+pub type UsbDevice = embassy_usb::UsbDevice<'static, Driver<'static, USB>>;
+pub type SerialClass = CdcAcmClass<'static, Driver<'static, USB>>;
+
+/// pid.codes test VID/PID, fine for a device that never leaves the pits
+const USB_VID: u16 = 0x1209;
+const USB_PID: u16 = 0x0001;
+
+static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+static CONSOLE_STATE: StaticCell<State> = StaticCell::new();
+static TELEMETRY_STATE: StaticCell<State> = StaticCell::new();
+
+/// USB device with two CDC-ACM serial ports: the text console first, then
+/// the binary telemetry stream
+///
+/// The device must be run in its own task for the ports to enumerate.
+pub fn new_usb(p: PeripheralsUsb) -> (UsbDevice, SerialClass, SerialClass) {
+    let driver = Driver::new(p.USB, Irqs);
+
+    let mut config = Config::new(USB_VID, USB_PID);
+    config.manufacturer = Some("Rust In Peace");
+    config.product = Some("Battle bot");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+    // Composite device with interface association descriptors, so hosts
+    // bind a serial driver to each port
+    config.device_class = 0xEF;
+    config.device_sub_class = 0x02;
+    config.device_protocol = 0x01;
+    config.composite_with_iads = true;
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let console = CdcAcmClass::new(&mut builder, CONSOLE_STATE.init(State::new()), 64);
+    let telemetry = CdcAcmClass::new(&mut builder, TELEMETRY_STATE.init(State::new()), 64);
+
+    (builder.build(), console, telemetry)
+}
@@ -0,0 +1,26 @@
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::pwm::{Config as PwmConfig, Pwm, PwmOutput};
+use rip_core::config::*;
+use rip_core::motor::RC_PULSE_MIN_US;
+use rip_core::weapon::WeaponController;
+
+use super::{PeripheralsWeapon, ServoController};
+
+/// Spinner ESC on PIN_20 (PWM slice 2 A)
+pub type Weapon = WeaponController<PwmOutput<'static>>;
+
+/// Build the weapon controller, holding the ESC at minimum throttle, and the
+/// servo on PIN_21 (slice 2 B), which shares the ESC's RC frame. The servo
+/// is driven by pulse width, so it follows any frame rate an ESC accepts.
+pub fn new_weapon_and_servo(p: PeripheralsWeapon) -> (Weapon, ServoController) {
+    let mut pwm_config = PwmConfig::default();
+    // 1MHz counting, one duty step per microsecond
+    pwm_config.divider = ((clk_sys_freq() / 1_000_000) as u8).into();
+    pwm_config.top = (WEAPON_PWM_PERIOD_US - 1) as u16; // 50Hz RC frame
+    pwm_config.compare_a = RC_PULSE_MIN_US as u16; // ESC arms on minimum throttle
+    pwm_config.compare_b = RC_PULSE_MIN_US as u16; // Servo at 0 degrees
+
+    let (esc, servo) = Pwm::new_output_ab(p.PWM_SLICE2, p.PIN_20, p.PIN_21, pwm_config).split();
+    let weapon = WeaponController::new(esc.unwrap(), WEAPON_PWM_PERIOD_US, WEAPON_SLEW_RATE).unwrap();
+    (weapon, ServoController::new(servo.unwrap()))
+}
@@ -0,0 +1,204 @@
+//! Controller input and receiver LED tasks (Core 0)
+//!
+//! The input loop is generic over [`InputSource`]: each receiver module only
+//! builds its source and hands it to [`run_input`], which stamps and
+//! publishes the frames and drives rumble. Core 1 sees the same normalized
+//! frames whichever receiver is fitted.
+//!
+//! Both tasks also watch core 1's heartbeat and status, so the driver hears
+//! about a stopped or faulted actuation core through rumble and the receiver
+//! LED rather than a bot that silently ignores the sticks.
+
+#[cfg(any(
+    all(feature = "rc-sbus", feature = "rc-crsf"),
+    all(feature = "rc-ppm", feature = "rc-pwm"),
+    all(feature = "rc-serial", feature = "rc-pulse"),
+))]
+compile_error!("pick one of the rc-sbus, rc-crsf, rc-ppm and rc-pwm features");
+
+#[cfg(not(any(feature = "rc-serial", feature = "rc-pulse")))]
+mod ps2;
+#[cfg(feature = "rc-pulse")]
+mod pulse;
+#[cfg(feature = "rc-serial")]
+mod rc;
+
+#[cfg(not(any(feature = "rc-serial", feature = "rc-pulse")))]
+pub use ps2::ps2_reader_task;
+#[cfg(feature = "rc-pulse")]
+pub use pulse::pulse_reader_task;
+#[cfg(feature = "rc-serial")]
+pub use rc::rc_reader_task;
+
+use defmt::*;
+use embassy_rp::gpio::{Level, Output};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::{Receiver, Sender};
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Ticker, Timer};
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::input::{ControllerData, InputSource, Rumble};
+use rip_core::settings::Settings;
+use rip_core::state::{BotState, Status};
+use rip_core::watchdog::WatchedTask;
+
+use crate::hardware::PeripheralsPs2Led;
+use crate::safety::{check_in, panicked_core, HeartbeatMonitor, ACTUATION_HEARTBEAT, INPUT_HEARTBEAT};
+
+/// Read frames from `source` forever, publishing each for core 1
+pub async fn run_input<S: InputSource>(
+    mut source: S,
+    controller_sender: Sender<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    mut battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    mut status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    mut settings_receiver: Receiver<'static, CriticalSectionRawMutex, Settings, 2>,
+    led_signal: &'static Signal<CriticalSectionRawMutex, ()>,
+) -> ! {
+    let mut settings = settings_receiver.get().await;
+
+    let mut actuation = HeartbeatMonitor::new(&ACTUATION_HEARTBEAT);
+    let mut emergency_since: Option<u64> = None;
+    let mut sequence: u32 = 0;
+    // Sources that wait on their receiver are paced by it; the rest, like
+    // the PS2 port's blocking SPI poll, never yield inside `read`, so they
+    // are polled once per control loop tick to let the other core 0 tasks run
+    let mut ticker = Ticker::every(Duration::from_hz(settings.control_loop_hz as u64));
+
+    loop {
+        if !S::PACES_ITSELF {
+            ticker.next().await;
+        }
+
+        // Prove to core 1 that this loop is still running, even while the
+        // receiver itself is missing
+        INPUT_HEARTBEAT.beat();
+        check_in(WatchedTask::InputReader);
+
+        if let Some(new) = settings_receiver.try_changed() {
+            if new.control_loop_hz != settings.control_loop_hz {
+                ticker = Ticker::every(Duration::from_hz(new.control_loop_hz as u64));
+            }
+            settings = new;
+        }
+
+        let mut controller_data = match source.read().await {
+            Ok(data) => data,
+            Err(e) => {
+                info!("Controller read error: {}", e);
+                if !S::PACES_ITSELF {
+                    Timer::after_millis(CONTROLLER_TIMEOUT_MS).await;
+                }
+                continue;
+            }
+        };
+
+        // Good frame - signal LED pulse
+        led_signal.signal(());
+
+        controller_data.timestamp_ms = Instant::now().as_millis();
+        controller_data.sequence = sequence;
+        sequence = sequence.wrapping_add(1);
+
+        // Overwrites any sample core 1 has not picked up yet, never blocks
+        controller_sender.send(controller_data);
+
+        // Simple rumble based on triggers
+        let mut rumble = Rumble {
+            small: controller_data.left_trigger > settings.rumble_threshold,
+            big: if controller_data.right_trigger > settings.rumble_threshold {
+                ((controller_data
+                    .right_trigger
+                    .saturating_sub(settings.rumble_max_subtract) as u16
+                    * 255)
+                    / settings.rumble_max_divisor) as u8
+            } else {
+                0
+            },
+        };
+
+        // Low battery: periodic full rumble so the driver notices mid-fight
+        let battery_low = battery_receiver
+            .try_get()
+            .is_some_and(|battery| battery.level.is_low());
+        if battery_low && controller_data.timestamp_ms % BATTERY_RUMBLE_PERIOD_MS < BATTERY_RUMBLE_ON_MS {
+            rumble.big = 255;
+        }
+
+        let status = status_receiver.try_get();
+        let now_ms = controller_data.timestamp_ms;
+
+        // Arming countdown: a short buzz every second
+        if status.is_some_and(|status| status.counting_down()) && now_ms % 1000 < ARM_RUMBLE_ON_MS {
+            rumble.big = 255;
+        }
+
+        // Entering an emergency: one long full rumble
+        let emergency = status.is_some_and(|status| matches!(status.state, BotState::Emergency(_)));
+        emergency_since = match emergency_since {
+            Some(since) if emergency => Some(since),
+            _ if emergency => Some(now_ms),
+            _ => None,
+        };
+        if emergency_since.is_some_and(|since| now_ms - since < EMERGENCY_RUMBLE_MS) {
+            rumble.big = 255;
+        }
+
+        // Core 1 stopped: nothing the driver does will move the bot, so keep
+        // buzzing until it comes back or the watchdog resets us
+        if !actuation_alive(&mut actuation) {
+            rumble.small = true;
+        }
+
+        source.set_rumble(rumble);
+    }
+}
+
+/// False once core 1 has panicked or its control loop has stopped ticking
+fn actuation_alive(monitor: &mut HeartbeatMonitor) -> bool {
+    monitor.is_alive() && panicked_core() != Some(1)
+}
+
+/// Receiver LED: fast blink while core 1 is not responding, slow blink while
+/// it holds the bot in an emergency, otherwise lit while controller frames
+/// are arriving
+#[embassy_executor::task]
+pub async fn receiver_led_task(
+    ps2_led: PeripheralsPs2Led,
+    mut status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    led_signal: &'static Signal<CriticalSectionRawMutex, ()>,
+) {
+    info!("Receiver LED task starting...");
+
+    let mut led = Output::new(ps2_led.PIN_22, Level::Low);
+    let mut actuation = HeartbeatMonitor::new(&ACTUATION_HEARTBEAT);
+    let mut last_read: Option<Instant> = None;
+
+    loop {
+        Timer::after_millis(RECEIVER_LED_FAULT_BLINK_MS).await;
+
+        if led_signal.try_take().is_some() {
+            last_read = Some(Instant::now());
+        }
+
+        let now_ms = Instant::now().as_millis();
+        let emergency = status_receiver
+            .try_get()
+            .is_some_and(|status| matches!(status.state, BotState::Emergency(_)));
+
+        let on = if !actuation_alive(&mut actuation) {
+            (now_ms / RECEIVER_LED_FAULT_BLINK_MS) % 2 == 0
+        } else if emergency {
+            (now_ms / RECEIVER_LED_EMERGENCY_BLINK_MS) % 2 == 0
+        } else {
+            // Stay lit for 500ms after each good read
+            last_read.is_some_and(|at| at.elapsed().as_millis() < 500)
+        };
+
+        if on {
+            led.set_high();
+        } else {
+            led.set_low();
+        }
+    }
+}
\ No newline at end of file
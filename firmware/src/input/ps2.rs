@@ -0,0 +1,130 @@
+//! PS2 DualShock 2 controller on SPI1
+
+use defmt::*;
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::peripherals::SPI1;
+use embassy_rp::spi::{self, Blocking, Spi};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::{Receiver, Sender};
+use pscontroller_rs::classic::GamepadButtons;
+use pscontroller_rs::{dualshock::ControlDS, Device, PlayStationPort};
+use rip_core::battery::BatteryStatus;
+use rip_core::input::{centred_axis, Buttons, ControllerData, InputError, InputSource, LinkQuality, Rumble};
+use rip_core::settings::Settings;
+use rip_core::state::Status;
+
+use super::run_input;
+use crate::hardware::PeripheralsController;
+
+/// Translate PS2 buttons into the controller-agnostic button set
+pub fn ps2_buttons(ps2: &GamepadButtons) -> Buttons {
+    let mut buttons = Buttons::default();
+    buttons.set(Buttons::SELECT, ps2.select());
+    buttons.set(Buttons::L3, ps2.l3());
+    buttons.set(Buttons::R3, ps2.r3());
+    buttons.set(Buttons::START, ps2.start());
+    buttons.set(Buttons::UP, ps2.up());
+    buttons.set(Buttons::RIGHT, ps2.right());
+    buttons.set(Buttons::DOWN, ps2.down());
+    buttons.set(Buttons::LEFT, ps2.left());
+    buttons.set(Buttons::L2, ps2.l2());
+    buttons.set(Buttons::R2, ps2.r2());
+    buttons.set(Buttons::L1, ps2.l1());
+    buttons.set(Buttons::R1, ps2.r1());
+    buttons.set(Buttons::TRIANGLE, ps2.triangle());
+    buttons.set(Buttons::CIRCLE, ps2.circle());
+    buttons.set(Buttons::CROSS, ps2.cross());
+    buttons.set(Buttons::SQUARE, ps2.square());
+    buttons
+}
+
+type Ps2Port = PlayStationPort<Spi<'static, SPI1, Blocking>, Output<'static>>;
+
+/// Wired DualShock 2, polled over blocking SPI; rumble rides along with
+/// each poll
+pub struct Ps2Source {
+    port: Ps2Port,
+    rumble: Rumble,
+}
+
+impl Ps2Source {
+    pub fn new(p: PeripheralsController, settings: &Settings) -> Self {
+        let mut config = spi::Config::default();
+        config.frequency = settings.ps2_spi_frequency;
+        config.polarity = spi::Polarity::IdleHigh;
+        config.phase = spi::Phase::CaptureOnSecondTransition;
+
+        let spi = Spi::new_blocking(
+            p.SPI1,
+            p.PIN_14, // SCK
+            p.PIN_15, // MOSI
+            p.PIN_12, // MISO
+            config,
+        );
+
+        let cs = Output::new(p.PIN_13, Level::High);
+        let mut port = PlayStationPort::new(spi, Some(cs));
+        port.enable_pressure().unwrap();
+
+        Ps2Source { port, rumble: Rumble::default() }
+    }
+}
+
+impl InputSource for Ps2Source {
+    async fn read(&mut self) -> Result<ControllerData, InputError> {
+        let motor_cmd = ControlDS::new(self.rumble.small, self.rumble.big);
+        let device = self
+            .port
+            .read_input(Some(&motor_cmd))
+            .map_err(|_| InputError::NoResponse)?;
+
+        let Device::DualShock2(controller) = device else {
+            info!("Not a DualShock 2 controller");
+            return Err(InputError::Unsupported);
+        };
+
+        Ok(ControllerData {
+            left_x: centred_axis(controller.lx),
+            // PS2 Y reads 0 at the top
+            left_y: -centred_axis(controller.ly),
+            right_x: centred_axis(controller.rx),
+            right_y: -centred_axis(controller.ry),
+            left_trigger: controller.pressures[0],
+            right_trigger: controller.pressures[1],
+            buttons: ps2_buttons(&controller.buttons),
+            link: LinkQuality::WIRED,
+            ..ControllerData::neutral(0)
+        })
+    }
+
+    fn set_rumble(&mut self, rumble: Rumble) {
+        self.rumble = rumble;
+    }
+}
+
+#[embassy_executor::task]
+pub async fn ps2_reader_task(
+    controller_peripherals: PeripheralsController,
+    controller_sender: Sender<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    mut settings_receiver: Receiver<'static, CriticalSectionRawMutex, Settings, 2>,
+    led_signal: &'static Signal<CriticalSectionRawMutex, ()>,
+) {
+    info!("PS2 reader task starting...");
+
+    // The SPI clock is only set here, so a new frequency needs a restart
+    let settings = settings_receiver.get().await;
+    let source = Ps2Source::new(controller_peripherals, &settings);
+
+    run_input(
+        source,
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        settings_receiver,
+        led_signal,
+    )
+    .await
+}
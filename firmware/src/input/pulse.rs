@@ -0,0 +1,145 @@
+//! PPM or PWM RC receiver, captured with PIO
+//!
+//! The `rc-ppm` feature reads one PPM wire on GPIO13; `rc-pwm` reads one
+//! channel per pin on GPIO12 to GPIO15, as listed in `RC_PWM_CHANNELS`.
+//! Either way the pulses become an `RcFrame` and go through
+//! `RC_CHANNEL_MAP` like SBUS and CRSF, and a channel that goes quiet turns
+//! the frame into a failsafe one.
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::{Receiver, Sender};
+use embassy_time::Instant;
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::input::{ControllerData, InputError, InputSource};
+use rip_core::pulse::PulseChannels;
+use rip_core::rc::RcMapper;
+use rip_core::settings::Settings;
+use rip_core::state::Status;
+
+#[cfg(feature = "rc-ppm")]
+use embassy_time::{with_timeout, Duration};
+#[cfg(feature = "rc-ppm")]
+use rip_core::pulse::{ppm_channel_mask, PpmDecoder, PpmEvent};
+#[cfg(feature = "rc-pwm")]
+use rip_core::pulse::channel_mask;
+#[cfg(feature = "rc-pwm")]
+use embassy_time::Timer;
+
+use super::run_input;
+use crate::hardware::{PeripheralsController, PulseCapture};
+
+/// PWM receivers repeat every 20 ms or so, so read all pins at that rate
+#[cfg(feature = "rc-pwm")]
+const PWM_READ_PERIOD_MS: u64 = 20;
+
+pub struct PulseSource {
+    capture: PulseCapture,
+    pulses: PulseChannels,
+    mapper: RcMapper,
+    #[cfg(feature = "rc-ppm")]
+    ppm: PpmDecoder,
+    /// Lost channels last reported, to log only changes
+    lost: u16,
+}
+
+impl PulseSource {
+    pub fn new(p: PeripheralsController) -> Self {
+        // Watch the mapped channels the PPM stream carries, or the wired
+        // PWM ones
+        #[cfg(feature = "rc-ppm")]
+        let expected = ppm_channel_mask(&RC_CHANNEL_MAP, PPM_CHANNEL_COUNT);
+        #[cfg(feature = "rc-pwm")]
+        let expected = channel_mask(RC_PWM_CHANNELS);
+
+        PulseSource {
+            capture: PulseCapture::new(p),
+            pulses: PulseChannels::new(expected),
+            mapper: RcMapper::new(&RC_CHANNEL_MAP),
+            #[cfg(feature = "rc-ppm")]
+            ppm: PpmDecoder::new(),
+            lost: 0,
+        }
+    }
+
+    fn controller_data(&mut self, now_ms: u64) -> ControllerData {
+        let lost = self.pulses.lost(now_ms);
+        if lost != self.lost {
+            if lost != 0 {
+                warn!("RC channels lost: {=u16:#b}", lost);
+            } else {
+                info!("RC channels back");
+            }
+            self.lost = lost;
+        }
+        self.mapper.map(&self.pulses.frame(now_ms), now_ms)
+    }
+
+    /// Feed intervals to the PPM decoder until a frame ends
+    #[cfg(feature = "rc-ppm")]
+    async fn next_frame(&mut self) {
+        loop {
+            let interval = self.capture.next_interval().await;
+            match self.ppm.push(interval) {
+                Some(PpmEvent::Channel(channel, width_us)) => {
+                    self.pulses.update(channel, width_us, Instant::now().as_millis());
+                }
+                Some(PpmEvent::FrameEnd(_)) => return,
+                None => {}
+            }
+        }
+    }
+}
+
+impl InputSource for PulseSource {
+    // PPM reads time out on their own and PWM reads never fail
+    const PACES_ITSELF: bool = true;
+
+    #[cfg(feature = "rc-ppm")]
+    async fn read(&mut self) -> Result<ControllerData, InputError> {
+        // A receiver that stops the stream on failsafe leaves nothing to
+        // time; the capture picks up where it was when the stream returns
+        with_timeout(Duration::from_millis(CONTROLLER_TIMEOUT_MS), self.next_frame())
+            .await
+            .map_err(|_| InputError::NoResponse)?;
+        Ok(self.controller_data(Instant::now().as_millis()))
+    }
+
+    #[cfg(feature = "rc-pwm")]
+    async fn read(&mut self) -> Result<ControllerData, InputError> {
+        // Pins run on their own clocks, so poll rather than wait on any one;
+        // a pin that stops shows up as a lost channel
+        Timer::after_millis(PWM_READ_PERIOD_MS).await;
+        let now_ms = Instant::now().as_millis();
+        for (pin, &channel) in RC_PWM_CHANNELS.iter().enumerate() {
+            while let Some(width_us) = self.capture.try_pulse(pin) {
+                self.pulses.update(channel.wrapping_sub(1), width_us, now_ms);
+            }
+        }
+        Ok(self.controller_data(now_ms))
+    }
+}
+
+#[embassy_executor::task]
+pub async fn pulse_reader_task(
+    controller_peripherals: PeripheralsController,
+    controller_sender: Sender<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    settings_receiver: Receiver<'static, CriticalSectionRawMutex, Settings, 2>,
+    led_signal: &'static Signal<CriticalSectionRawMutex, ()>,
+) {
+    info!("Pulse reader task starting...");
+
+    run_input(
+        PulseSource::new(controller_peripherals),
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        settings_receiver,
+        led_signal,
+    )
+    .await
+}
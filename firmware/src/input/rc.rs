@@ -0,0 +1,108 @@
+//! SBUS or CRSF RC receiver on UART0
+//!
+//! The protocol is picked at build time with the `rc-sbus` or `rc-crsf`
+//! feature. Channels go through `RC_CHANNEL_MAP`; see `rip_core::rc` for how
+//! switches stand in for the PS2 gestures.
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::{Receiver, Sender};
+use embassy_time::{with_timeout, Duration, Instant};
+use embedded_io_async::Read;
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::input::{ControllerData, InputError, InputSource};
+use rip_core::rc::{RcDecoder, RcFrame, RcMapper};
+use rip_core::settings::Settings;
+use rip_core::state::Status;
+
+#[cfg(feature = "rc-crsf")]
+use rip_core::crsf::CrsfDecoder as Decoder;
+#[cfg(feature = "rc-sbus")]
+use rip_core::sbus::SbusDecoder as Decoder;
+
+use super::run_input;
+use crate::hardware::{new_rc_uart, PeripheralsController, RcUart};
+
+/// A receiver frame is never more than this many UART bytes away
+const READ_CHUNK: usize = 64;
+
+pub struct RcSource {
+    uart: RcUart,
+    decoder: Decoder,
+    mapper: RcMapper,
+    /// Bytes read but not yet decoded, kept when a read holds more than one
+    /// frame
+    chunk: [u8; READ_CHUNK],
+    pos: usize,
+    len: usize,
+}
+
+impl RcSource {
+    pub fn new(p: PeripheralsController) -> Self {
+        RcSource {
+            uart: new_rc_uart(p),
+            decoder: Decoder::new(),
+            mapper: RcMapper::new(&RC_CHANNEL_MAP),
+            chunk: [0; READ_CHUNK],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Decode until a frame comes out, skipping corrupt ones
+    async fn next_frame(&mut self) -> RcFrame {
+        loop {
+            while self.pos < self.len {
+                self.decoder.push(self.chunk[self.pos]);
+                self.pos += 1;
+                match self.decoder.next_frame() {
+                    Some(Ok(frame)) => return frame,
+                    Some(Err(e)) => debug!("RC frame error: {}", e),
+                    None => {}
+                }
+            }
+
+            // Line errors leave garbage that the decoder resyncs past
+            self.len = self.uart.read(&mut self.chunk).await.unwrap_or(0);
+            self.pos = 0;
+        }
+    }
+}
+
+impl InputSource for RcSource {
+    const PACES_ITSELF: bool = true;
+
+    async fn read(&mut self) -> Result<ControllerData, InputError> {
+        // A receiver with no signal may send nothing at all, or only
+        // garbage, which is as good as a lost link. Unread bytes stay in the
+        // UART buffer if this times out.
+        let frame = with_timeout(Duration::from_millis(CONTROLLER_TIMEOUT_MS), self.next_frame())
+            .await
+            .map_err(|_| InputError::NoResponse)?;
+        Ok(self.mapper.map(&frame, Instant::now().as_millis()))
+    }
+}
+
+#[embassy_executor::task]
+pub async fn rc_reader_task(
+    controller_peripherals: PeripheralsController,
+    controller_sender: Sender<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    settings_receiver: Receiver<'static, CriticalSectionRawMutex, Settings, 2>,
+    led_signal: &'static Signal<CriticalSectionRawMutex, ()>,
+) {
+    info!("RC reader task starting...");
+
+    run_input(
+        RcSource::new(controller_peripherals),
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        settings_receiver,
+        led_signal,
+    )
+    .await
+}
@@ -0,0 +1,263 @@
+//! Dual-core battle bot control system
+//! Core 0: Control & Decision Making
+//! Core 1: Real-Time Peripheral Control
+
+#![no_std]
+#![no_main]
+#![allow(dead_code)]
+#![allow(unused_assignments)]
+
+mod input;
+mod hardware;
+
+mod blackbox;
+mod console;
+mod control;
+mod safety;
+mod sensors;
+mod settings;
+mod telemetry;
+
+use defmt::*;
+use embassy_executor::Executor;
+use embassy_rp::multicore::{spawn_core1, Stack};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::Watch;
+use static_cell::StaticCell;
+use defmt_rtt as _;
+
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::console::MotorTest;
+use rip_core::current::DriveCurrent;
+use rip_core::imu::Orientation;
+use rip_core::events::{TankDriveEvent, ServoEvent, WeaponEvent, LedEvent, EmergencyReason};
+use rip_core::input::ControllerData;
+use rip_core::settings::Settings;
+use rip_core::state::Status;
+use rip_core::telemetry::DriveOutput;
+use rip_core::timing::LoopStats;
+use hardware::{new_flash, new_usb, new_weapon_and_servo, split_peripherals};
+use blackbox::blackbox_task;
+use console::{console_task, usb_task, ConsoleLinks};
+use input::receiver_led_task;
+#[cfg(not(any(feature = "rc-serial", feature = "rc-pulse")))]
+use input::ps2_reader_task;
+#[cfg(feature = "rc-serial")]
+use input::rc_reader_task;
+#[cfg(feature = "rc-pulse")]
+use input::pulse_reader_task;
+use control::{state_controller_task, tank_driver_task, servo_driver_task, weapon_driver_task, led_driver_task};
+use sensors::{analog_sensor_task, imu_task};
+use safety::{check_reset_reason, new_watchdog, take_panic_record, watchdog_task};
+use settings::load_settings;
+use telemetry::telemetry_task;
+
+/// Latest-value mailbox between cores: a new frame replaces the old one, so
+/// core 1 always sees the freshest sample and core 0 never blocks. The
+/// console and telemetry read it too.
+static CONTROLLER_WATCH: Watch<CriticalSectionRawMutex, ControllerData, 3> = Watch::new();
+static TANK_CHANNEL: Channel<CriticalSectionRawMutex, TankDriveEvent, COMMAND_CHANNEL_SIZE> =
+    Channel::new();
+static SERVO_CHANNEL: Channel<CriticalSectionRawMutex, ServoEvent, COMMAND_CHANNEL_SIZE> =
+    Channel::new();
+static WEAPON_CHANNEL: Channel<CriticalSectionRawMutex, WeaponEvent, COMMAND_CHANNEL_SIZE> =
+    Channel::new();
+static LED_CHANNEL: Channel<CriticalSectionRawMutex, LedEvent, COMMAND_CHANNEL_SIZE> =
+    Channel::new();
+/// Bot state from core 1, for rumble, receiver LED, blackbox, console and telemetry on core 0
+static STATUS_WATCH: Watch<CriticalSectionRawMutex, Status, 5> = Watch::new();
+/// Latest drive current per side, for the tank driver, blackbox and console
+static CURRENT_WATCH: Watch<CriticalSectionRawMutex, DriveCurrent, 3> = Watch::new();
+/// Latest IMU orientation, for heading hold and flip detection
+static ORIENTATION_WATCH: Watch<CriticalSectionRawMutex, Orientation, 2> = Watch::new();
+/// Latest battery reading, for the state controller, rumble, blackbox, console and telemetry
+static BATTERY_WATCH: Watch<CriticalSectionRawMutex, BatteryStatus, 5> = Watch::new();
+/// Runtime settings, loaded from flash by core 0 before anything reads them
+static SETTINGS_WATCH: Watch<CriticalSectionRawMutex, Settings, 2> = Watch::new();
+/// Ramped drive speeds and control loop timing from core 1, for telemetry
+static DRIVE_OUTPUT_WATCH: Watch<CriticalSectionRawMutex, DriveOutput, 1> = Watch::new();
+static LOOP_STATS_WATCH: Watch<CriticalSectionRawMutex, LoopStats, 1> = Watch::new();
+static LED_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+/// Any task may raise an emergency by signalling a reason here
+static EMERGENCY_SIGNAL: Signal<CriticalSectionRawMutex, EmergencyReason> = Signal::new();
+/// Why the last boot ended, if it was a hang or panic; kept apart from
+/// `EMERGENCY_SIGNAL` so a sensor fault raised first cannot overwrite it
+static BOOT_EMERGENCY_SIGNAL: Signal<CriticalSectionRawMutex, EmergencyReason> = Signal::new();
+/// Console motor tests, handed to the state machine on core 1
+static MOTOR_TEST_SIGNAL: Signal<CriticalSectionRawMutex, MotorTest> = Signal::new();
+/// Console settings save, carried out by the blackbox task that owns the flash
+static SETTINGS_SAVE_REQUEST: Signal<CriticalSectionRawMutex, Settings> = Signal::new();
+static SETTINGS_SAVE_RESULT: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+static mut CORE1_STACK: Stack<CORE1_STACK_SIZE> = Stack::new();
+static EXECUTOR0: StaticCell<Executor> = StaticCell::new();
+static EXECUTOR1: StaticCell<Executor> = StaticCell::new();
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    let p = embassy_rp::init(Default::default());
+
+    let (core1, p0, p1) = split_peripherals(p);
+
+    spawn_core1(
+        core1,
+        unsafe { &mut *core::ptr::addr_of_mut!(CORE1_STACK) },
+        move || {
+            let executor1 = EXECUTOR1.init(Executor::new());
+            executor1.run(|spawner| {
+                spawner.must_spawn(core1_main(spawner, p1));
+            });
+        },
+    );
+
+    let executor0 = EXECUTOR0.init(Executor::new());
+    executor0.run(|spawner| {
+        spawner.must_spawn(core0_main(spawner, p0));
+    });
+}
+
+#[embassy_executor::task]
+async fn core0_main(spawner: embassy_executor::Spawner, p0: hardware::Peripherals0) {
+    info!("Core 0 starting...");
+
+    // Come back up disabled after a hang or panic; core 1 picks this up on
+    // its first tick
+    let mut watchdog = new_watchdog(p0.watchdog);
+    let panic = take_panic_record();
+    if let Some(reason) = check_reset_reason(&mut watchdog, panic.as_ref()) {
+        BOOT_EMERGENCY_SIGNAL.signal(reason);
+    }
+
+    let mut flash = new_flash(p0.flash);
+    SETTINGS_WATCH.sender().send(load_settings(&mut flash));
+
+    let controller_sender = CONTROLLER_WATCH.sender();
+    let battery_receiver = BATTERY_WATCH.receiver().unwrap();
+    let status_receiver = STATUS_WATCH.receiver().unwrap();
+
+    #[cfg(not(any(feature = "rc-serial", feature = "rc-pulse")))]
+    spawner.must_spawn(ps2_reader_task(
+        p0.controller,
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        SETTINGS_WATCH.receiver().unwrap(),
+        &LED_SIGNAL,
+    ));
+    #[cfg(feature = "rc-serial")]
+    spawner.must_spawn(rc_reader_task(
+        p0.controller,
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        SETTINGS_WATCH.receiver().unwrap(),
+        &LED_SIGNAL,
+    ));
+    #[cfg(feature = "rc-pulse")]
+    spawner.must_spawn(pulse_reader_task(
+        p0.controller,
+        controller_sender,
+        battery_receiver,
+        status_receiver,
+        SETTINGS_WATCH.receiver().unwrap(),
+        &LED_SIGNAL,
+    ));
+    spawner.must_spawn(receiver_led_task(
+        p0.ps2_led,
+        STATUS_WATCH.receiver().unwrap(),
+        &LED_SIGNAL,
+    ));
+    spawner.must_spawn(watchdog_task(watchdog));
+    spawner.must_spawn(blackbox_task(
+        flash,
+        panic,
+        STATUS_WATCH.receiver().unwrap(),
+        BATTERY_WATCH.receiver().unwrap(),
+        CURRENT_WATCH.receiver().unwrap(),
+        &SETTINGS_SAVE_REQUEST,
+        &SETTINGS_SAVE_RESULT,
+    ));
+
+    let (usb, console_class, telemetry_class) = new_usb(p0.usb);
+    spawner.must_spawn(usb_task(usb));
+    spawner.must_spawn(console_task(
+        console_class,
+        ConsoleLinks {
+            controller_receiver: CONTROLLER_WATCH.receiver().unwrap(),
+            status_receiver: STATUS_WATCH.receiver().unwrap(),
+            battery_receiver: BATTERY_WATCH.receiver().unwrap(),
+            current_receiver: CURRENT_WATCH.receiver().unwrap(),
+            settings_sender: SETTINGS_WATCH.sender(),
+            save_request: &SETTINGS_SAVE_REQUEST,
+            save_result: &SETTINGS_SAVE_RESULT,
+            motor_test_signal: &MOTOR_TEST_SIGNAL,
+            emergency_signal: &EMERGENCY_SIGNAL,
+        },
+    ));
+    spawner.must_spawn(telemetry_task(
+        telemetry_class,
+        CONTROLLER_WATCH.receiver().unwrap(),
+        STATUS_WATCH.receiver().unwrap(),
+        BATTERY_WATCH.receiver().unwrap(),
+        DRIVE_OUTPUT_WATCH.receiver().unwrap(),
+        LOOP_STATS_WATCH.receiver().unwrap(),
+    ));
+}
+
+#[embassy_executor::task]
+async fn core1_main(spawner: embassy_executor::Spawner, p1: hardware::Peripherals1) {
+    info!("Core 1 starting...");
+
+    let controller_receiver = CONTROLLER_WATCH.receiver().unwrap();
+    let battery_receiver = BATTERY_WATCH.receiver().unwrap();
+    let orientation_receiver = ORIENTATION_WATCH.receiver().unwrap();
+    let tank_sender = TANK_CHANNEL.sender();
+    let tank_receiver = TANK_CHANNEL.receiver();
+    let servo_sender = SERVO_CHANNEL.sender();
+    let servo_receiver = SERVO_CHANNEL.receiver();
+    let weapon_sender = WEAPON_CHANNEL.sender();
+    let weapon_receiver = WEAPON_CHANNEL.receiver();
+    let led_sender = LED_CHANNEL.sender();
+    let led_receiver = LED_CHANNEL.receiver();
+
+    // Spawn the state controller (the "brains")
+    spawner.must_spawn(state_controller_task(
+        controller_receiver,
+        battery_receiver,
+        orientation_receiver,
+        SETTINGS_WATCH.receiver().unwrap(),
+        tank_sender,
+        servo_sender,
+        weapon_sender,
+        led_sender,
+        STATUS_WATCH.sender(),
+        &EMERGENCY_SIGNAL,
+        &BOOT_EMERGENCY_SIGNAL,
+        LOOP_STATS_WATCH.sender(),
+        &MOTOR_TEST_SIGNAL,
+    ));
+
+    // Spawn hardware driver tasks
+    spawner.must_spawn(tank_driver_task(
+        p1.motor,
+        tank_receiver,
+        CURRENT_WATCH.receiver().unwrap(),
+        ORIENTATION_WATCH.receiver().unwrap(),
+        DRIVE_OUTPUT_WATCH.sender(),
+    ));
+    // The servo rides on the weapon ESC's PWM slice
+    let (weapon, servo) = new_weapon_and_servo(p1.weapon);
+    spawner.must_spawn(servo_driver_task(servo, servo_receiver));
+    spawner.must_spawn(weapon_driver_task(weapon, weapon_receiver));
+    spawner.must_spawn(led_driver_task(p1.state_led, led_receiver));
+    spawner.must_spawn(analog_sensor_task(
+        p1.analog,
+        CURRENT_WATCH.sender(),
+        BATTERY_WATCH.sender(),
+        &EMERGENCY_SIGNAL,
+    ));
+    spawner.must_spawn(imu_task(p1.imu, ORIENTATION_WATCH.sender()));
+}
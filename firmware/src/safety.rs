@@ -0,0 +1,244 @@
+//! Cross-core fault detection: heartbeats in both directions, panic
+//! reporting and the hardware watchdog
+//!
+//! Each core bumps its own heartbeat counter every time round its main loop,
+//! and the panic handler records which core died. Core 1 treats a quiet input
+//! core as link loss and a panic as an emergency; core 0 watches the
+//! actuation heartbeat so the driver is told when core 1 stops.
+//!
+//! Critical tasks on both cores also check in with [`HEARTBEATS`]; the
+//! watchdog is only fed while all of them are alive, and the task that
+//! stopped is left in a watchdog scratch register for the next boot to log.
+//!
+//! A panic de-energizes every actuator before anything else, then leaves its
+//! location in RAM the runtime does not zero and resets (release) or halts
+//! under the debugger (debug).
+
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use defmt::*;
+use embassy_rp::watchdog::{ResetReason, Watchdog};
+use embassy_time::{Duration, Instant, Ticker};
+use portable_atomic::AtomicU32;
+
+use rip_core::config::*;
+use rip_core::events::EmergencyReason;
+use rip_core::panic_record::PanicRecord;
+use rip_core::watchdog::{HeartbeatRegistry, WatchedTask};
+
+use crate::hardware::{force_safe_outputs, PeripheralsWatchdog};
+
+const NO_PANIC: u8 = u8::MAX;
+
+/// Bumped by the PS2 reader on core 0
+pub static INPUT_HEARTBEAT: Heartbeat = Heartbeat::new();
+/// Bumped by the state controller on core 1
+pub static ACTUATION_HEARTBEAT: Heartbeat = Heartbeat::new();
+
+static PANICKED_CORE: AtomicU8 = AtomicU8::new(NO_PANIC);
+
+/// Location of the last panic, kept across resets in `.uninit`
+#[link_section = ".uninit.PANIC_RECORD"]
+static mut PANIC_RECORD: MaybeUninit<PanicRecord> = MaybeUninit::uninit();
+
+/// Check-ins from every task the watchdog waits on
+pub static HEARTBEATS: HeartbeatRegistry = HeartbeatRegistry::new(TASK_STARTUP_GRACE_MS);
+
+/// Scratch register holding the task that starved the watchdog
+const STALL_SCRATCH: usize = 0;
+/// Upper bits marking the scratch value as ours, the low byte is the task
+const STALL_MAGIC: u32 = 0x5AFE_0000;
+
+/// Called by a watched task each time round its loop
+pub fn check_in(task: WatchedTask) {
+    HEARTBEATS.check_in(task, Instant::now().as_millis());
+}
+
+/// Counter one core bumps and the other watches
+pub struct Heartbeat(AtomicU32);
+
+impl Heartbeat {
+    pub const fn new() -> Self {
+        Heartbeat(AtomicU32::new(0))
+    }
+
+    /// Called by the owning core each time round its loop
+    pub fn beat(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Core that panicked, if any
+pub fn panicked_core() -> Option<u8> {
+    match PANICKED_CORE.load(Ordering::Acquire) {
+        NO_PANIC => None,
+        core => Some(core),
+    }
+}
+
+/// Tracks the other core's heartbeat
+pub struct HeartbeatMonitor {
+    heartbeat: &'static Heartbeat,
+    last_count: u32,
+    last_change: Instant,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(heartbeat: &'static Heartbeat) -> Self {
+        Self {
+            heartbeat,
+            last_count: heartbeat.count(),
+            last_change: Instant::now(),
+        }
+    }
+
+    /// Returns false once the heartbeat has not moved for `HEARTBEAT_TIMEOUT_MS`
+    pub fn is_alive(&mut self) -> bool {
+        let count = self.heartbeat.count();
+        if count != self.last_count {
+            self.last_count = count;
+            self.last_change = Instant::now();
+        }
+        self.last_change.elapsed() <= Duration::from_millis(HEARTBEAT_TIMEOUT_MS)
+    }
+}
+
+/// Take the panic record left by the previous boot, if there is one
+pub fn take_panic_record() -> Option<PanicRecord> {
+    // Any bit pattern is a possible record; `is_valid` sorts out the garbage
+    let slot = core::ptr::addr_of_mut!(PANIC_RECORD).cast::<PanicRecord>();
+    unsafe {
+        let record = slot.read_volatile();
+        slot.write_volatile(PanicRecord::empty());
+        record.is_valid().then_some(record)
+    }
+}
+
+/// Log why the chip last reset and clear the stall record
+///
+/// `panic` is the record from [`take_panic_record`]. Returns the emergency to
+/// start in if the reset came from a fault, so the bot comes up disabled
+/// rather than trusting whatever went wrong before.
+pub fn check_reset_reason(watchdog: &mut Watchdog, panic: Option<&PanicRecord>) -> Option<EmergencyReason> {
+    let stall = watchdog.get_scratch(STALL_SCRATCH);
+    watchdog.set_scratch(STALL_SCRATCH, 0);
+
+    if let Some(record) = panic {
+        error!(
+            "Core {} panicked last boot at {}:{}:{}",
+            record.core,
+            record.file(),
+            record.line,
+            record.column
+        );
+        // The panic handler resets through the watchdog
+        if matches!(watchdog.reset_reason(), Some(ResetReason::Forced)) {
+            return Some(EmergencyReason::CorePanic);
+        }
+    }
+
+    match watchdog.reset_reason() {
+        Some(ResetReason::TimedOut) => {
+            let task = (stall & !0xFF == STALL_MAGIC)
+                .then(|| WatchedTask::from_index(stall as u8))
+                .flatten();
+            match task {
+                Some(task) => error!("Watchdog reset: {} stopped checking in", task),
+                // The feeder itself hung, so nothing was recorded
+                None => error!("Watchdog reset: watchdog task stopped"),
+            }
+            Some(EmergencyReason::WatchdogReset)
+        }
+        Some(ResetReason::Forced) => {
+            warn!("Forced watchdog reset");
+            None
+        }
+        None => {
+            info!("Power-on reset");
+            None
+        }
+    }
+}
+
+/// Feeds the hardware watchdog while every watched task is alive
+///
+/// Once a task goes stale this records which one and stops feeding; the
+/// watchdog then resets the chip within `WATCHDOG_TIMEOUT_MS`, which also
+/// drops every PWM output.
+///
+/// Core 0's executor is cooperative, so this only runs while every core 0
+/// task yields: the input task on its receiver or control loop ticker, the
+/// receiver LED, blackbox and telemetry tasks on timers, and the USB and
+/// console tasks on the USB driver. The longest blocking stretch is a
+/// blackbox sector erase of tens of milliseconds, well inside
+/// `WATCHDOG_TIMEOUT_MS`.
+#[embassy_executor::task]
+pub async fn watchdog_task(mut watchdog: Watchdog) {
+    info!("Watchdog task starting...");
+
+    watchdog.pause_on_debug(true);
+    watchdog.start(Duration::from_millis(WATCHDOG_TIMEOUT_MS));
+    let mut ticker = Ticker::every(Duration::from_millis(WATCHDOG_FEED_MS));
+
+    loop {
+        ticker.next().await;
+
+        match HEARTBEATS.stale(Instant::now().as_millis(), TASK_HEARTBEAT_TIMEOUT_MS) {
+            None => watchdog.feed(),
+            Some(task) => {
+                error!("{} stopped checking in, letting the watchdog reset", task);
+                watchdog.set_scratch(STALL_SCRATCH, STALL_MAGIC | task as u32);
+                // Stop feeding for good; the reset is coming
+                loop {
+                    ticker.next().await;
+                }
+            }
+        }
+    }
+}
+
+/// Take the watchdog peripheral for [`check_reset_reason`] and [`watchdog_task`]
+pub fn new_watchdog(p: PeripheralsWatchdog) -> Watchdog {
+    Watchdog::new(p.WATCHDOG)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // Motors, weapon and servo off before anything that could fault again
+    force_safe_outputs();
+
+    // The other core polls this every tick and drops into an emergency.
+    // Only the first panic is kept; a second core panicking is a symptom.
+    let core = embassy_rp::pac::SIO.cpuid().read() as u8;
+    if panicked_core().is_none() {
+        PANICKED_CORE.store(core, Ordering::Release);
+
+        if let Some(location) = info.location() {
+            let record = PanicRecord::new(core, location.file(), location.line(), location.column());
+            let slot = core::ptr::addr_of_mut!(PANIC_RECORD).cast::<PanicRecord>();
+            unsafe { slot.write_volatile(record) };
+        }
+    }
+
+    defmt::error!("Core {} panicked: {}", core, defmt::Display2Format(info));
+
+    // Under the debugger, stop where it happened
+    #[cfg(debug_assertions)]
+    cortex_m::asm::udf();
+
+    // In the arena, come back up in an emergency with the record to log
+    #[cfg(not(debug_assertions))]
+    {
+        let mut watchdog = Watchdog::new(unsafe { embassy_rp::peripherals::WATCHDOG::steal() });
+        watchdog.trigger_reset();
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Sensor tasks (Core 1): drive current, battery voltage and IMU
+
+use defmt::*;
+use embassy_rp::adc::{self, Adc, Channel as AdcChannel};
+use embassy_rp::bind_interrupts;
+use embassy_rp::gpio::Pull;
+use embassy_rp::i2c::{self, I2c};
+use embassy_rp::peripherals::I2C0;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::Sender;
+use embassy_time::{Duration, Instant, Ticker, Timer};
+
+use rip_core::battery::{BatteryLevel, BatteryMonitor, BatteryStatus};
+use rip_core::config::*;
+use rip_core::current::{CurrentLimiter, DriveCurrent};
+use rip_core::events::EmergencyReason;
+use rip_core::imu::{GyroCalibration, Mpu6050, Orientation, OrientationTracker, MPU6050_ADDRESS};
+
+use crate::hardware::{PeripheralsAnalog, PeripheralsImu};
+
+bind_interrupts!(struct Irqs {
+    ADC_IRQ_FIFO => adc::InterruptHandler;
+    I2C0_IRQ => i2c::InterruptHandler<I2C0>;
+});
+
+/// Current samples per battery sample
+const BATTERY_DIVIDER: u32 = (BATTERY_SAMPLE_MS / CURRENT_SAMPLE_MS) as u32;
+
+/// Owns the ADC and samples everything on it
+///
+/// Drive current is read every `CURRENT_SAMPLE_MS` and published with each
+/// side's duty limit for the tank driver. A stalled side raises an
+/// `OverCurrent` emergency and a flat pack a `LowBattery` one; both keep
+/// signalling while the condition lasts so the bot cannot be re-armed into it.
+#[embassy_executor::task]
+pub async fn analog_sensor_task(
+    analog_peripherals: PeripheralsAnalog,
+    current_sender: Sender<'static, CriticalSectionRawMutex, DriveCurrent, 3>,
+    battery_sender: Sender<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    emergency_signal: &'static Signal<CriticalSectionRawMutex, EmergencyReason>,
+) {
+    info!("Analog sensor task starting...");
+
+    let mut adc = Adc::new(analog_peripherals.ADC, Irqs, adc::Config::default());
+    let mut left_pin = AdcChannel::new_pin(analog_peripherals.PIN_27, Pull::None);
+    let mut battery_pin = AdcChannel::new_pin(analog_peripherals.PIN_28, Pull::None);
+    let mut right_pin = AdcChannel::new_pin(analog_peripherals.PIN_26, Pull::None);
+
+    let mut left_current = CurrentLimiter::new(CURRENT_CONFIG);
+    let mut right_current = CurrentLimiter::new(CURRENT_CONFIG);
+    let mut battery = BatteryMonitor::new(BATTERY_CONFIG);
+    let mut battery_level = BatteryLevel::Normal;
+    let mut stalled = false;
+    let mut sample: u32 = 0;
+    let mut ticker = Ticker::every(Duration::from_millis(CURRENT_SAMPLE_MS));
+
+    loop {
+        ticker.next().await;
+        sample = sample.wrapping_add(1);
+
+        match (adc.read(&mut left_pin).await, adc.read(&mut right_pin).await) {
+            (Ok(left_raw), Ok(right_raw)) => {
+                let current = DriveCurrent {
+                    left: left_current.update(left_raw, CURRENT_SAMPLE_MS as u32),
+                    right: right_current.update(right_raw, CURRENT_SAMPLE_MS as u32),
+                };
+                current_sender.send(current);
+
+                let now_stalled = current.left.stalled || current.right.stalled;
+                if now_stalled && !stalled {
+                    error!("Drive stalled: L={} mA, R={} mA", current.left.average_ma, current.right.average_ma);
+                }
+                stalled = now_stalled;
+                if stalled {
+                    emergency_signal.signal(EmergencyReason::OverCurrent);
+                }
+            }
+            _ => warn!("Current sense ADC read failed"),
+        }
+
+        if sample % BATTERY_DIVIDER != 0 {
+            continue;
+        }
+
+        let raw = match adc.read(&mut battery_pin).await {
+            Ok(raw) => raw,
+            Err(e) => {
+                warn!("Battery ADC read failed: {}", e);
+                continue;
+            }
+        };
+
+        let status = battery.update(raw);
+        if status.level != battery_level {
+            if status.level == BatteryLevel::Absent {
+                info!("No battery connected ({} mV), running on USB power", status.millivolts);
+            } else {
+                info!("Battery {} at {} mV", status.level, status.millivolts);
+            }
+            battery_level = status.level;
+        }
+        battery_sender.send(status);
+
+        // Keep signalling so the bot cannot be re-armed on a flat pack
+        if status.level == BatteryLevel::Critical {
+            emergency_signal.signal(EmergencyReason::LowBattery);
+        }
+    }
+}
+
+/// Reads the MPU-6050 and publishes the orientation estimate
+///
+/// The gyro bias is averaged over `IMU_CALIBRATION_SAMPLES` at boot, so the
+/// bot has to be left still while powering up. A failed read publishes a
+/// zero yaw rate so heading hold stops steering on stale data.
+#[embassy_executor::task]
+pub async fn imu_task(
+    imu_peripherals: PeripheralsImu,
+    orientation_sender: Sender<'static, CriticalSectionRawMutex, Orientation, 2>,
+) {
+    info!("IMU task starting...");
+
+    let mut config = i2c::Config::default();
+    config.frequency = IMU_I2C_FREQUENCY;
+    let bus = I2c::new_async(
+        imu_peripherals.I2C0,
+        imu_peripherals.PIN_1, // SCL
+        imu_peripherals.PIN_0, // SDA
+        Irqs,
+        config,
+    );
+    let mut imu = Mpu6050::new(bus, MPU6050_ADDRESS);
+
+    while let Err(e) = imu.init().await {
+        warn!("IMU init failed: {}, retrying", e);
+        Timer::after_millis(1000).await;
+    }
+
+    let mut ticker = Ticker::every(Duration::from_millis(IMU_SAMPLE_MS));
+    let mut calibration = GyroCalibration::new();
+    while calibration.count() < IMU_CALIBRATION_SAMPLES {
+        ticker.next().await;
+        if let Ok(sample) = imu.read().await {
+            calibration.add(&sample);
+        }
+    }
+    let bias = calibration.bias();
+    info!("Gyro bias: {} mdps", bias);
+
+    let mut tracker = OrientationTracker::new(bias);
+    let mut last = Instant::now();
+    let mut orientation = Orientation::default();
+
+    loop {
+        ticker.next().await;
+
+        match imu.read().await {
+            Ok(sample) => {
+                let now = Instant::now();
+                orientation = tracker.update(&sample, (now - last).as_micros() as u32);
+                last = now;
+            }
+            Err(e) => {
+                warn!("IMU read failed: {}", e);
+                orientation.yaw_rate_mdps = 0;
+            }
+        }
+        orientation_sender.send(orientation);
+    }
+}
@@ -0,0 +1,46 @@
+//! Runtime settings in their own flash sector
+//!
+//! Loaded once at boot by core 0 and published on a watch, so the tasks that
+//! use them pick up later changes too. The PS2 SPI frequency is the
+//! exception: the port is built once, so it only changes after a restart.
+
+use defmt::*;
+use embassy_rp::flash::Error as FlashError;
+
+use rip_core::config::{SETTINGS_OFFSET, SETTINGS_SIZE};
+use rip_core::settings::{Settings, SettingsError, RECORD_SIZE};
+
+use crate::hardware::SystemFlash;
+
+/// Saved settings, or the compile-time defaults if none are usable
+pub fn load_settings(flash: &mut SystemFlash) -> Settings {
+    let mut bytes = [0; RECORD_SIZE];
+    if let Err(e) = flash.blocking_read(SETTINGS_OFFSET, &mut bytes) {
+        warn!("Settings read failed ({:?}), using defaults", e);
+        return Settings::DEFAULT;
+    }
+
+    match Settings::decode(&bytes) {
+        Ok(settings) => {
+            info!("Loaded settings: {}", settings);
+            settings
+        }
+        Err(SettingsError::Blank) => {
+            info!("No saved settings, using defaults");
+            Settings::DEFAULT
+        }
+        Err(e) => {
+            warn!("Saved settings rejected ({}), using defaults", e);
+            Settings::DEFAULT
+        }
+    }
+}
+
+/// Replace the saved settings
+///
+/// Erasing the sector stalls both cores for tens of milliseconds, so only
+/// call this with the drive disarmed.
+pub fn save_settings(flash: &mut SystemFlash, settings: &Settings) -> Result<(), FlashError> {
+    flash.blocking_erase(SETTINGS_OFFSET, SETTINGS_OFFSET + SETTINGS_SIZE)?;
+    flash.blocking_write(SETTINGS_OFFSET, &settings.encode())
+}
@@ -0,0 +1,84 @@
+//! Telemetry stream task (Core 0)
+//!
+//! Streams the latest stick input, drive output, state, battery and loop
+//! timing as binary frames on the second USB serial port, for `rip_ground`
+//! in the pit. See `rip_core::telemetry` for the format.
+//!
+//! Nothing is buffered while no host is listening; a ground station that
+//! connects mid-match simply starts from the current values.
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::Receiver;
+use embassy_time::{Duration, Instant, Ticker};
+use embassy_usb::driver::EndpointError;
+
+use rip_core::battery::BatteryStatus;
+use rip_core::config::*;
+use rip_core::input::ControllerData;
+use rip_core::state::Status;
+use rip_core::telemetry::{DriveOutput, Frame, Message, MAX_FRAME_SIZE};
+use rip_core::timing::LoopStats;
+
+use crate::hardware::SerialClass;
+
+const PACKET_SIZE: usize = 64;
+
+/// One of each message
+const MESSAGES: usize = 5;
+
+#[embassy_executor::task]
+pub async fn telemetry_task(
+    mut class: SerialClass,
+    mut controller_receiver: Receiver<'static, CriticalSectionRawMutex, ControllerData, 3>,
+    mut status_receiver: Receiver<'static, CriticalSectionRawMutex, Status, 5>,
+    mut battery_receiver: Receiver<'static, CriticalSectionRawMutex, BatteryStatus, 5>,
+    mut drive_receiver: Receiver<'static, CriticalSectionRawMutex, DriveOutput, 1>,
+    mut loop_stats_receiver: Receiver<'static, CriticalSectionRawMutex, LoopStats, 1>,
+) {
+    info!("Telemetry task starting...");
+
+    loop {
+        class.wait_connection().await;
+        info!("Telemetry connected");
+
+        let mut ticker = Ticker::every(Duration::from_millis(TELEMETRY_PERIOD_MS));
+        loop {
+            ticker.next().await;
+
+            let messages = [
+                controller_receiver.try_get().map(Message::Input),
+                drive_receiver.try_get().map(Message::Drive),
+                status_receiver.try_get().map(Message::Status),
+                battery_receiver.try_get().map(Message::Battery),
+                loop_stats_receiver.try_get().map(Message::Loop),
+            ];
+
+            let timestamp_ms = Instant::now().as_millis() as u32;
+            let mut batch = [0; MESSAGES * MAX_FRAME_SIZE];
+            let mut len = 0;
+            for message in messages.into_iter().flatten() {
+                let frame = Frame { timestamp_ms, message }.encode(&mut [0; MAX_FRAME_SIZE]);
+                batch[len..len + frame.len()].copy_from_slice(frame);
+                len += frame.len();
+            }
+
+            if send(&mut class, &batch[..len]).await.is_err() {
+                break;
+            }
+        }
+        info!("Telemetry disconnected");
+    }
+}
+
+/// Write a batch in packets; frames may straddle packet boundaries
+async fn send(class: &mut SerialClass, bytes: &[u8]) -> Result<(), EndpointError> {
+    for packet in bytes.chunks(PACKET_SIZE) {
+        class.write_packet(packet).await?;
+    }
+    // A full last packet needs a zero-length one to end the transfer
+    if !bytes.is_empty() && bytes.len().is_multiple_of(PACKET_SIZE) {
+        class.write_packet(&[]).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Decode a flash blackbox dump into a readable timeline
+//!
+//! Usage: `rip_blackbox <image.bin>`
+//!
+//! The image is either just the blackbox region or a dump of the whole
+//! flash, for example from `picotool save -r 0x101F0000 0x10200000 blackbox.bin`
+//! or `picotool save -a flash.bin`.
+
+use std::process::ExitCode;
+
+use rip_core::blackbox::{entries, Entry, Event};
+use rip_core::config::{BLACKBOX_OFFSET, BLACKBOX_SIZE, FLASH_SIZE};
+use rip_core::state::BotState;
+
+/// The blackbox region out of either kind of dump
+fn region(image: &[u8]) -> Result<&[u8], String> {
+    let start = BLACKBOX_OFFSET as usize;
+    let size = BLACKBOX_SIZE as usize;
+    match image.len() {
+        len if len == size => Ok(image),
+        len if len == FLASH_SIZE => Ok(&image[start..start + size]),
+        len => Err(format!(
+            "image is {len} bytes, expected the {size} byte blackbox region or a {FLASH_SIZE} byte flash dump"
+        )),
+    }
+}
+
+/// One line per entry, oldest first
+fn timeline(region: &[u8]) -> Vec<String> {
+    let mut log: Vec<Entry> = entries(region).collect();
+    log.sort_by_key(|entry| entry.sequence);
+
+    let mut lines = Vec::new();
+    if let Some(first) = log.first().filter(|first| first.sequence > 0) {
+        lines.push(format!("({} older entries overwritten)", first.sequence));
+    }
+
+    let mut boot = 0;
+    for entry in &log {
+        if entry.event == Event::Boot {
+            boot += 1;
+        }
+        lines.push(format!(
+            "boot {boot:>3}  {}  {}",
+            uptime(entry.timestamp_ms),
+            describe(entry.event)
+        ));
+    }
+    lines
+}
+
+/// Uptime as minutes, seconds and milliseconds
+fn uptime(ms: u32) -> String {
+    format!("{:>4}:{:02}.{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+fn describe(event: Event) -> String {
+    match event {
+        Event::Boot => "boot".into(),
+        Event::State(BotState::LinkLost) => "link lost".into(),
+        Event::State(BotState::Emergency(reason)) => {
+            format!("EMERGENCY: {reason:?} (code {})", reason.code())
+        }
+        Event::State(state) => format!("state {state:?}"),
+        Event::Weapon(weapon) => format!("weapon {weapon:?}"),
+        Event::BatteryMin(mv) => format!("battery min {}.{:02} V", mv / 1000, mv % 1000 / 10),
+        Event::CurrentPeak(ma) => format!("current peak {}.{:02} A", ma / 1000, ma % 1000 / 10),
+        Event::Panic { core, line } => format!("PANIC on core {core} at line {line} (previous boot)"),
+        Event::Dropped(count) => format!("{count} events dropped"),
+    }
+}
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: rip_blackbox <image.bin>");
+        return ExitCode::FAILURE;
+    };
+
+    let result = std::fs::read(&path)
+        .map_err(|e| format!("{path}: {e}"))
+        .and_then(|image| Ok(timeline(region(&image)?)));
+
+    match result {
+        Ok(lines) if lines.is_empty() => {
+            println!("blackbox is empty");
+            ExitCode::SUCCESS
+        }
+        Ok(lines) => {
+            for line in lines {
+                println!("{line}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rip_core::blackbox::ENTRY_SIZE;
+    use rip_core::events::EmergencyReason;
+    use rip_core::state::WeaponState;
+
+    /// Erased region with entries written at the given slots
+    fn image(entries: &[(usize, Entry)]) -> Vec<u8> {
+        let mut image = vec![0xFF; BLACKBOX_SIZE as usize];
+        for (slot, entry) in entries {
+            image[slot * ENTRY_SIZE..][..ENTRY_SIZE].copy_from_slice(&entry.encode());
+        }
+        image
+    }
+
+    fn entry(sequence: u32, timestamp_ms: u32, event: Event) -> Entry {
+        Entry {
+            sequence,
+            timestamp_ms,
+            event,
+        }
+    }
+
+    #[test]
+    fn timeline_sorts_across_wrap_and_counts_boots() {
+        // The ring wrapped: the newest entries sit at the start of the region
+        let image = image(&[
+            (0, entry(41, 75_250, Event::State(BotState::Emergency(EmergencyReason::OverCurrent)))),
+            (1, entry(42, 0, Event::Boot)),
+            (300, entry(38, 0, Event::Boot)),
+            (301, entry(39, 12, Event::State(BotState::Idle))),
+            (302, entry(40, 65_000, Event::BatteryMin(10_950))),
+        ]);
+
+        assert_eq!(
+            timeline(&image),
+            [
+                "(38 older entries overwritten)",
+                "boot   1     0:00.000  boot",
+                "boot   1     0:00.012  state Idle",
+                "boot   1     1:05.000  battery min 10.95 V",
+                "boot   1     1:15.250  EMERGENCY: OverCurrent (code 3)",
+                "boot   2     0:00.000  boot",
+            ]
+        );
+    }
+
+    #[test]
+    fn torn_entries_are_skipped() {
+        let mut image = image(&[
+            (0, entry(0, 0, Event::Boot)),
+            (1, entry(1, 500, Event::Weapon(WeaponState::Armed))),
+            (2, entry(2, 900, Event::Panic { core: 1, line: 312 })),
+        ]);
+        image[ENTRY_SIZE + 4] ^= 0xFF;
+
+        assert_eq!(
+            timeline(&image),
+            [
+                "boot   1     0:00.000  boot",
+                "boot   1     0:00.900  PANIC on core 1 at line 312 (previous boot)",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_region_in_full_flash_dump() {
+        let mut flash = vec![0xFF; FLASH_SIZE];
+        let region_image = image(&[(0, entry(0, 0, Event::Boot))]);
+        flash[BLACKBOX_OFFSET as usize..][..region_image.len()].copy_from_slice(&region_image);
+
+        assert_eq!(timeline(region(&flash).unwrap()), ["boot   1     0:00.000  boot"]);
+        assert!(region(&flash[..1000]).is_err());
+        assert!(timeline(region(&image(&[])).unwrap()).is_empty());
+    }
+}
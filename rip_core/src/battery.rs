@@ -0,0 +1,196 @@
+//! Battery voltage conversion, filtering and thresholds
+
+/// How the battery is wired to the ADC and where its limits are
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatteryConfig {
+    /// Divider ratio (R_top + R_bottom) / R_bottom, times 1000
+    pub divider_ratio_milli: u32,
+    /// ADC reference voltage in millivolts
+    pub adc_ref_mv: u32,
+    /// Number of LiPo cells in series
+    pub cells: u8,
+    /// Per-cell voltage below which the driver is warned
+    pub warn_mv_per_cell: u16,
+    /// Per-cell voltage below which the bot must stop drawing power
+    pub cutoff_mv_per_cell: u16,
+    /// Per-cell margin a reading must climb back above a threshold to clear it
+    pub hysteresis_mv_per_cell: u16,
+    /// Exponential filter strength: each sample moves the estimate 1/2^shift
+    pub filter_shift: u8,
+}
+
+impl BatteryConfig {
+    /// Battery voltage in millivolts for a raw 12-bit ADC reading
+    pub fn adc_to_millivolts(&self, raw: u16) -> u32 {
+        let pin_mv = raw.min(4095) as u32 * self.adc_ref_mv / 4095;
+        pin_mv * self.divider_ratio_milli / 1000
+    }
+
+    fn pack_mv(&self, per_cell: u16) -> u32 {
+        per_cell as u32 * self.cells as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BatteryLevel {
+    Normal,
+    /// Below the soft threshold: warn the driver
+    Low,
+    /// Below the hard LiPo cutoff: stop the bot
+    Critical,
+    /// Below even one cell's cutoff since boot: running on USB bench power
+    /// with the battery unplugged. Once a pack has been seen, losing the
+    /// reading is `Critical` instead, as it may be a broken sense wire.
+    Absent,
+}
+
+impl BatteryLevel {
+    /// A pack is connected and running down
+    pub fn is_low(self) -> bool {
+        matches!(self, BatteryLevel::Low | BatteryLevel::Critical)
+    }
+}
+
+/// Latest filtered battery reading
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatteryStatus {
+    pub millivolts: u32,
+    pub level: BatteryLevel,
+}
+
+/// Filters raw samples and classifies the result with hysteresis, so motor
+/// current sag on a single sample does not flip the level back and forth
+pub struct BatteryMonitor {
+    config: BatteryConfig,
+    /// Filtered voltage in millivolts, scaled by 2^filter_shift
+    filtered: u32,
+    /// A reading of at least one cell has been seen since boot
+    pack_seen: bool,
+    level: BatteryLevel,
+}
+
+impl BatteryMonitor {
+    pub const fn new(config: BatteryConfig) -> Self {
+        BatteryMonitor {
+            config,
+            filtered: 0,
+            pack_seen: false,
+            level: BatteryLevel::Normal,
+        }
+    }
+
+    /// Feed one raw ADC sample and get the updated status
+    pub fn update(&mut self, raw: u16) -> BatteryStatus {
+        let sample = self.config.adc_to_millivolts(raw);
+        let shift = self.config.filter_shift;
+
+        // Until a pack shows up every sample seeds the filter, so neither
+        // boot nor plugging a pack in on the bench ramps up from zero
+        self.filtered = if self.pack_seen {
+            self.filtered - (self.filtered >> shift) + sample
+        } else {
+            sample << shift
+        };
+        let millivolts = self.filtered >> shift;
+
+        let config = &self.config;
+        self.pack_seen |= millivolts >= config.cutoff_mv_per_cell as u32;
+        let warn = config.pack_mv(config.warn_mv_per_cell);
+        let cutoff = config.pack_mv(config.cutoff_mv_per_cell);
+        let margin = config.pack_mv(config.hysteresis_mv_per_cell);
+
+        self.level = match self.level {
+            _ if !self.pack_seen => BatteryLevel::Absent,
+            _ if millivolts < cutoff => BatteryLevel::Critical,
+            BatteryLevel::Critical if millivolts < cutoff + margin => BatteryLevel::Critical,
+            _ if millivolts < warn => BatteryLevel::Low,
+            BatteryLevel::Low | BatteryLevel::Critical if millivolts < warn + margin => BatteryLevel::Low,
+            _ => BatteryLevel::Normal,
+        };
+
+        BatteryStatus {
+            millivolts,
+            level: self.level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3S pack on a 4:1 divider with a 4095 mV reference: 1 count = 4 mV
+    const CONFIG: BatteryConfig = BatteryConfig {
+        divider_ratio_milli: 4000,
+        adc_ref_mv: 4095,
+        cells: 3,
+        warn_mv_per_cell: 3500,
+        cutoff_mv_per_cell: 3300,
+        hysteresis_mv_per_cell: 50,
+        filter_shift: 0,
+    };
+
+    fn raw(pack_mv: u32) -> u16 {
+        (pack_mv / 4) as u16
+    }
+
+    #[test]
+    fn converts_through_divider() {
+        assert_eq!(CONFIG.adc_to_millivolts(2100), 8400);
+        assert_eq!(CONFIG.adc_to_millivolts(u16::MAX), 16_380);
+    }
+
+    #[test]
+    fn levels_follow_thresholds_with_hysteresis() {
+        let mut monitor = BatteryMonitor::new(CONFIG);
+        assert_eq!(monitor.update(raw(12_000)).level, BatteryLevel::Normal);
+        assert_eq!(monitor.update(raw(10_400)).level, BatteryLevel::Low);
+
+        // Needs to clear 10_500 + 150 to go back to normal
+        assert_eq!(monitor.update(raw(10_600)).level, BatteryLevel::Low);
+        assert_eq!(monitor.update(raw(10_700)).level, BatteryLevel::Normal);
+
+        assert_eq!(monitor.update(raw(9_800)).level, BatteryLevel::Critical);
+        assert_eq!(monitor.update(raw(10_000)).level, BatteryLevel::Critical);
+        assert_eq!(monitor.update(raw(10_100)).level, BatteryLevel::Low);
+    }
+
+    #[test]
+    fn no_pack_is_absent_not_critical() {
+        let mut monitor = BatteryMonitor::new(CONFIG);
+        assert_eq!(monitor.update(0).level, BatteryLevel::Absent);
+        assert_eq!(monitor.update(raw(3_200)).level, BatteryLevel::Absent);
+        assert!(!BatteryLevel::Absent.is_low());
+
+        // Plugging a pack in classifies it as usual, without filtering up
+        // through the cutoff
+        let config = BatteryConfig { filter_shift: 3, ..CONFIG };
+        let mut monitor = BatteryMonitor::new(config);
+        assert_eq!(monitor.update(0).level, BatteryLevel::Absent);
+        assert_eq!(monitor.update(raw(12_000)).level, BatteryLevel::Normal);
+    }
+
+    #[test]
+    fn lost_reading_after_a_pack_is_critical() {
+        let mut monitor = BatteryMonitor::new(CONFIG);
+        assert_eq!(monitor.update(raw(12_000)).level, BatteryLevel::Normal);
+
+        // A broken sense wire must not disable the cutoff
+        assert_eq!(monitor.update(0).level, BatteryLevel::Critical);
+        assert_eq!(monitor.update(0).level, BatteryLevel::Critical);
+    }
+
+    #[test]
+    fn filter_smooths_single_sag() {
+        let config = BatteryConfig { filter_shift: 3, ..CONFIG };
+        let mut monitor = BatteryMonitor::new(config);
+        monitor.update(raw(12_000));
+
+        let status = monitor.update(raw(8_000));
+        assert_eq!(status.millivolts, 11_500);
+        assert_eq!(status.level, BatteryLevel::Normal);
+    }
+}
@@ -0,0 +1,323 @@
+//! Flash blackbox: fixed-size event entries in a ring of erasable sectors
+//!
+//! Each entry is 16 bytes: sequence number, uptime, event and a CRC. The
+//! sequence keeps counting across boots, so the newest entry is found by
+//! scanning for the highest valid sequence, and a dumped image sorts back
+//! into order the same way. Erased flash reads as all ones, which never
+//! passes the CRC, and neither does an entry torn by a power cut.
+//!
+//! Writing only needs the layout here; erasing, reading and programming the
+//! flash itself is left to the firmware, and decoding a dump to the host.
+
+use crate::crc::crc16;
+use crate::events::EmergencyReason;
+use crate::state::{BotState, Status, WeaponState};
+
+/// Bytes per entry; a power of two so entries never straddle a sector
+pub const ENTRY_SIZE: usize = 16;
+
+/// Smallest erasable unit of the QSPI flash
+pub const SECTOR_SIZE: usize = 4096;
+
+/// What happened
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// Firmware started; uptimes after this count from zero again
+    Boot,
+    /// Bot state changed, covering link loss and emergencies with a reason
+    State(BotState),
+    /// Weapon interlock changed
+    Weapon(WeaponState),
+    /// Lowest filtered battery voltage in millivolts since the last state change
+    BatteryMin(u32),
+    /// Highest filtered drive current in milliamps since the last state change
+    CurrentPeak(u32),
+    /// The previous boot panicked at this line
+    Panic { core: u8, line: u32 },
+    /// Events lost because the flash could not be written in time
+    Dropped(u32),
+}
+
+impl Event {
+    fn encode(self) -> (u8, u8, u32) {
+        match self {
+            Event::Boot => (1, 0, 0),
+            Event::State(state) => match state {
+                BotState::Idle => (2, 0, 0),
+                BotState::Arming => (2, 1, 0),
+                BotState::Combat => (2, 2, 0),
+                BotState::LinkLost => (2, 3, 0),
+                BotState::Emergency(reason) => (2, 4, reason.code() as u32),
+            },
+            Event::Weapon(weapon) => match weapon {
+                WeaponState::Safe => (3, 0, 0),
+                WeaponState::Arming => (3, 1, 0),
+                WeaponState::Armed => (3, 2, 0),
+            },
+            Event::BatteryMin(mv) => (4, 0, mv),
+            Event::CurrentPeak(ma) => (5, 0, ma),
+            Event::Panic { core, line } => (6, core, line),
+            Event::Dropped(count) => (7, 0, count),
+        }
+    }
+
+    fn decode(tag: u8, arg: u8, value: u32) -> Option<Self> {
+        Some(match (tag, arg) {
+            (1, _) => Event::Boot,
+            (2, 0) => Event::State(BotState::Idle),
+            (2, 1) => Event::State(BotState::Arming),
+            (2, 2) => Event::State(BotState::Combat),
+            (2, 3) => Event::State(BotState::LinkLost),
+            (2, 4) => Event::State(BotState::Emergency(EmergencyReason::from_code(value as u8)?)),
+            (3, 0) => Event::Weapon(WeaponState::Safe),
+            (3, 1) => Event::Weapon(WeaponState::Arming),
+            (3, 2) => Event::Weapon(WeaponState::Armed),
+            (4, _) => Event::BatteryMin(value),
+            (5, _) => Event::CurrentPeak(value),
+            (6, core) => Event::Panic { core, line: value },
+            (7, _) => Event::Dropped(value),
+            _ => return None,
+        })
+    }
+}
+
+/// One logged event
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Entry {
+    pub sequence: u32,
+    /// Uptime within its boot, wrapping after 49 days
+    pub timestamp_ms: u32,
+    pub event: Event,
+}
+
+impl Entry {
+    pub fn encode(&self) -> [u8; ENTRY_SIZE] {
+        let (tag, arg, value) = self.event.encode();
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..4].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes[8] = tag;
+        bytes[9] = arg;
+        bytes[10..14].copy_from_slice(&value.to_le_bytes());
+        let crc = crc16(&bytes[..14]);
+        bytes[14..16].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// None for erased, torn or unknown entries
+    pub fn decode(bytes: &[u8; ENTRY_SIZE]) -> Option<Self> {
+        let word = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        if u16::from_le_bytes([bytes[14], bytes[15]]) != crc16(&bytes[..14]) {
+            return None;
+        }
+        Some(Entry {
+            sequence: word(0),
+            timestamp_ms: word(4),
+            event: Event::decode(bytes[8], bytes[9], word(10))?,
+        })
+    }
+}
+
+/// True if the slot reads as freshly erased flash
+pub fn is_erased(bytes: &[u8; ENTRY_SIZE]) -> bool {
+    bytes.iter().all(|&byte| byte == 0xFF)
+}
+
+/// Valid entries of a blackbox region image, in storage order
+pub fn entries(image: &[u8]) -> impl Iterator<Item = Entry> + '_ {
+    image.as_chunks::<ENTRY_SIZE>().0.iter().filter_map(Entry::decode)
+}
+
+/// Where the next entry goes in a region of whole sectors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ring {
+    slots: u32,
+    next: u32,
+    sequence: u32,
+}
+
+impl Ring {
+    /// Resume after the newest entry, given the sequence number of every
+    /// slot in order (None for erased or invalid slots)
+    pub fn recover(region_len: usize, sequences: impl Iterator<Item = Option<u32>>) -> Self {
+        let slots = (region_len / ENTRY_SIZE) as u32;
+        let newest = sequences
+            .take(slots as usize)
+            .enumerate()
+            .filter_map(|(slot, sequence)| Some((slot as u32, sequence?)))
+            .max_by_key(|&(_, sequence)| sequence);
+
+        match newest {
+            Some((slot, sequence)) => Ring {
+                slots,
+                next: (slot + 1) % slots,
+                sequence: sequence.wrapping_add(1),
+            },
+            None => Ring { slots, next: 0, sequence: 0 },
+        }
+    }
+
+    /// Byte offset of the next slot within the region
+    pub fn offset(&self) -> usize {
+        self.next as usize * ENTRY_SIZE
+    }
+
+    /// True if the next slot starts a sector, which must be erased first.
+    /// This drops the oldest sector's worth of entries once the ring wraps.
+    pub fn needs_erase(&self) -> bool {
+        self.offset().is_multiple_of(SECTOR_SIZE)
+    }
+
+    /// Build the entry for the next slot and move past it
+    pub fn push(&mut self, timestamp_ms: u32, event: Event) -> Entry {
+        let entry = Entry {
+            sequence: self.sequence,
+            timestamp_ms,
+            event,
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        self.skip();
+        entry
+    }
+
+    /// Leave the next slot unused, for one that is neither erased nor valid
+    pub fn skip(&mut self) {
+        self.next = (self.next + 1) % self.slots;
+    }
+}
+
+/// Turns the status, battery and current streams into blackbox events
+///
+/// Minima are only logged when the state changes, covering the period that
+/// just ended, so a long fight costs two entries rather than a stream.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Recorder {
+    status: Option<Status>,
+    battery_min_mv: Option<u32>,
+    current_peak_ma: Option<u32>,
+}
+
+impl Recorder {
+    pub const fn new() -> Self {
+        Recorder {
+            status: None,
+            battery_min_mv: None,
+            current_peak_ma: None,
+        }
+    }
+
+    pub fn battery(&mut self, millivolts: u32) {
+        self.battery_min_mv = Some(self.battery_min_mv.map_or(millivolts, |min| min.min(millivolts)));
+    }
+
+    pub fn current(&mut self, milliamps: u32) {
+        self.current_peak_ma = Some(self.current_peak_ma.map_or(milliamps, |peak| peak.max(milliamps)));
+    }
+
+    /// Note the latest status and emit whatever changed
+    pub fn status(&mut self, status: Status, mut record: impl FnMut(Event)) {
+        let previous = self.status.replace(status);
+        if previous.map(|p| p.state) != Some(status.state) {
+            if let Some(mv) = self.battery_min_mv.take() {
+                record(Event::BatteryMin(mv));
+            }
+            if let Some(ma) = self.current_peak_ma.take() {
+                record(Event::CurrentPeak(ma));
+            }
+            record(Event::State(status.state));
+        }
+        if previous.map(|p| p.weapon) != Some(status.weapon) {
+            record(Event::Weapon(status.weapon));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENTS: [Event; 9] = [
+        Event::Boot,
+        Event::State(BotState::Combat),
+        Event::State(BotState::LinkLost),
+        Event::State(BotState::Emergency(EmergencyReason::OverCurrent)),
+        Event::Weapon(WeaponState::Armed),
+        Event::BatteryMin(10_950),
+        Event::CurrentPeak(2_400),
+        Event::Panic { core: 1, line: 312 },
+        Event::Dropped(3),
+    ];
+
+    #[test]
+    fn entries_round_trip() {
+        for (sequence, event) in EVENTS.into_iter().enumerate() {
+            let entry = Entry {
+                sequence: sequence as u32,
+                timestamp_ms: 123_456,
+                event,
+            };
+            assert_eq!(Entry::decode(&entry.encode()), Some(entry));
+        }
+    }
+
+    #[test]
+    fn erased_and_corrupt_entries_are_rejected() {
+        let erased = [0xFF; ENTRY_SIZE];
+        assert!(is_erased(&erased));
+        assert_eq!(Entry::decode(&erased), None);
+
+        let mut bytes = Entry { sequence: 1, timestamp_ms: 2, event: Event::Boot }.encode();
+        bytes[5] ^= 0x10;
+        assert_eq!(Entry::decode(&bytes), None);
+    }
+
+    #[test]
+    fn ring_resumes_after_newest_and_wraps() {
+        let region = 2 * SECTOR_SIZE;
+        let slots = region / ENTRY_SIZE;
+
+        let empty = Ring::recover(region, core::iter::repeat(None));
+        assert_eq!((empty.offset(), empty.needs_erase()), (0, true));
+
+        // Wrapped once: the newest entry sits just before older ones
+        let sequences = (0..slots).map(|slot| Some(if slot < 5 { slots + slot } else { slot } as u32));
+        let mut ring = Ring::recover(region, sequences);
+        assert_eq!(ring.offset(), 5 * ENTRY_SIZE);
+        assert_eq!(ring.push(0, Event::Boot).sequence, slots as u32 + 5);
+
+        let mut ring = Ring::recover(region, (0..slots).map(|slot| Some(slot as u32)));
+        assert_eq!(ring.offset(), 0);
+        assert!(ring.needs_erase());
+        ring.push(0, Event::Boot);
+        assert!(!ring.needs_erase());
+    }
+
+    #[test]
+    fn recorder_logs_changes_and_minima() {
+        let mut recorder = Recorder::new();
+        let mut events = Vec::new();
+        let status = |state, weapon| Status { state, weapon };
+
+        recorder.status(status(BotState::Idle, WeaponState::Safe), |e| events.push(e));
+        recorder.battery(11_800);
+        recorder.battery(11_200);
+        recorder.current(900);
+        recorder.current(600);
+        recorder.status(status(BotState::Idle, WeaponState::Safe), |e| events.push(e));
+        recorder.status(status(BotState::Combat, WeaponState::Armed), |e| events.push(e));
+
+        assert_eq!(
+            events,
+            [
+                Event::State(BotState::Idle),
+                Event::Weapon(WeaponState::Safe),
+                Event::BatteryMin(11_200),
+                Event::CurrentPeak(900),
+                Event::State(BotState::Combat),
+                Event::Weapon(WeaponState::Armed),
+            ]
+        );
+    }
+}
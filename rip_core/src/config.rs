@@ -0,0 +1,347 @@
+//! Configuration constants for the battle bot
+//!
+//! This module contains all the magic numbers and configuration values
+//! used throughout the system, making them easy to find and modify.
+//!
+//! Values a driver tunes at the pit table are also in
+//! [`Settings`](crate::settings::Settings); the constants here are only its
+//! defaults, used until a valid record has been saved to flash.
+
+use crate::battery::BatteryConfig;
+use crate::current::CurrentConfig;
+use crate::input::Buttons;
+use crate::motor::MotorCalibration;
+use crate::rc::{ChannelMapping, RcFunction};
+use crate::slew::SlewRate;
+use crate::tank_drive::HeadingHold;
+
+// Controller Configuration
+/// PS2 controller SPI frequency in Hz
+pub const PS2_SPI_FREQUENCY: u32 = 10_000;
+
+/// Stick dead zones, in raw counts either side of centre (128)
+pub const STICK_DEAD_ZONE: u8 = 10;
+pub const SPIN_DEAD_ZONE: u8 = 20;
+
+/// Control loop frequency in Hz
+pub const CONTROL_LOOP_HZ: u32 = 60;
+
+/// Controller feedback thresholds
+pub const RUMBLE_THRESHOLD: u8 = 30;
+pub const RUMBLE_MAX_SUBTRACT: u8 = 30;
+pub const RUMBLE_MAX_DIVISOR: u16 = 225;
+
+/// Button pressure thresholds
+/// L2 and R2 must both be held past this to arm the drive
+pub const COMBAT_MODE_PRESSURE: u8 = 100;
+
+// RC Receiver Configuration (`rc-sbus` / `rc-crsf` features)
+/// Which radio channel drives what, for the usual AETR channel order with
+/// the arm, weapon and kill switches on channels 5 to 7. Add
+/// `.reversed()` to an entry if it works backwards on your radio.
+pub const RC_CHANNEL_MAP: [ChannelMapping; 7] = [
+    ChannelMapping::new(1, RcFunction::Steer),
+    ChannelMapping::new(2, RcFunction::Throttle),
+    ChannelMapping::new(3, RcFunction::Weapon),
+    ChannelMapping::new(4, RcFunction::Spin),
+    ChannelMapping::new(5, RcFunction::ArmSwitch),
+    ChannelMapping::new(6, RcFunction::WeaponSwitch),
+    ChannelMapping::new(7, RcFunction::KillSwitch),
+];
+
+/// How long the button gesture for a switch flip is held, so core 1 sees it
+pub const RC_SWITCH_PULSE_MS: u64 = 100;
+
+/// Radio channel on each PWM capture pin, GPIO12 to GPIO15 (`rc-pwm`
+/// feature). Four wires carry the drive, weapon throttle and arm switch;
+/// the weapon switch needs a fifth channel, so use PPM for a full setup.
+pub const RC_PWM_CHANNELS: [u8; 4] = [1, 2, 3, 5];
+
+/// A PPM gap at least this long ends the frame; channels are never over
+/// 2200 µs
+pub const PPM_SYNC_GAP_US: u32 = 3000;
+
+/// Channels the PPM receiver sends (`rc-ppm` feature). Only mapped channels
+/// up to this one are watched for loss; any past it read centre.
+pub const PPM_CHANNEL_COUNT: u8 = 8;
+
+/// A PWM or PPM channel with no good pulse for this long is lost. Receivers
+/// repeat every 20 ms or so.
+pub const PULSE_LOSS_MS: u64 = 100;
+
+// Arming Configuration
+/// Start must follow the L2+R2 hold within this window to begin arming
+pub const ARM_WINDOW_MS: u64 = 1000;
+
+/// Drive arming countdown, shown on the LED and with rumble
+pub const ARM_COUNTDOWN_MS: u64 = 3000;
+
+/// Weapon arms with L1 held plus this button, only once the drive is armed
+pub const WEAPON_ARM_BUTTON: u16 = Buttons::CROSS;
+
+/// Weapon arming countdown
+pub const WEAPON_ARM_COUNTDOWN_MS: u64 = 3000;
+
+/// Drops the weapon back to safe, from arming or armed
+pub const WEAPON_DISARM_BUTTON: u16 = Buttons::CIRCLE;
+
+/// Arming countdown rumble: pulse length at the start of each second
+pub const ARM_RUMBLE_ON_MS: u64 = 150;
+
+// Motor Configuration
+/// Per-motor invert, trim and duty limits, applied on every drive command.
+/// FL is mounted mirrored, so it runs inverted.
+pub const MOTOR_CAL_FL: MotorCalibration = MotorCalibration { invert: true, ..MotorCalibration::DEFAULT };
+pub const MOTOR_CAL_FR: MotorCalibration = MotorCalibration::DEFAULT;
+pub const MOTOR_CAL_BL: MotorCalibration = MotorCalibration::DEFAULT;
+pub const MOTOR_CAL_BR: MotorCalibration = MotorCalibration::DEFAULT;
+
+/// Drive ramp rates in percent of full speed per second (0 = unlimited).
+/// Braking is faster than accelerating so the bot still stops quickly.
+pub const DRIVE_SLEW_RATE: SlewRate = SlewRate {
+    accel_per_s: 400,
+    decel_per_s: 800,
+};
+
+/// Period of the tank driver ramp ticker
+pub const DRIVE_RAMP_TICK_MS: u64 = 10;
+
+// Battery Configuration
+/// 3S LiPo on a 47k/10k divider into ADC2 (PIN_28). Below the warning level
+/// the LED and controller rumble warn the driver; below the cutoff the drive
+/// is disabled with a `LowBattery` emergency. Until a reading of at least one
+/// cell is seen there is no pack, as on USB bench power, and nothing is raised.
+pub const BATTERY_CONFIG: BatteryConfig = BatteryConfig {
+    divider_ratio_milli: 5700,
+    adc_ref_mv: 3300,
+    cells: 3,
+    warn_mv_per_cell: 3500,
+    cutoff_mv_per_cell: 3300,
+    hysteresis_mv_per_cell: 50,
+    filter_shift: 3,
+};
+
+/// Battery sampling period
+pub const BATTERY_SAMPLE_MS: u64 = 100;
+
+/// Low battery rumble: pulse length and repeat period
+pub const BATTERY_RUMBLE_ON_MS: u64 = 500;
+pub const BATTERY_RUMBLE_PERIOD_MS: u64 = 10_000;
+
+// Current Sensing Configuration
+/// Per-side shunt amplifier (10 mOhm shunt, 50 V/V gain) and limits sized for
+/// the TB6612FNG: 3.2 A peak, 1.2 A continuous per channel
+pub const CURRENT_CONFIG: CurrentConfig = CurrentConfig {
+    mv_per_amp: 500,
+    adc_ref_mv: 3300,
+    peak_limit_ma: 3000,
+    average_limit_ma: 1200,
+    average_shift: 4,
+    derate_step: 5,
+    recover_step: 1,
+    min_duty: 30,
+    stall_ms: 1000,
+};
+
+/// Current sampling period; battery is sampled every
+/// `BATTERY_SAMPLE_MS / CURRENT_SAMPLE_MS` current samples
+pub const CURRENT_SAMPLE_MS: u64 = 5;
+
+// Weapon Configuration
+/// Spinner ESC ramps in percent of full throttle per second. Spin-up is slow
+/// to spare the battery; spin-down is faster but still ramped, since an
+/// instant cut makes the ESC brake the spinner hard.
+pub const WEAPON_SLEW_RATE: SlewRate = SlewRate {
+    accel_per_s: 50,
+    decel_per_s: 100,
+};
+
+/// Period of the weapon ramp ticker
+pub const WEAPON_RAMP_TICK_MS: u64 = 10;
+
+/// Weapon ESC PWM frame length (50 Hz)
+pub const WEAPON_PWM_PERIOD_US: u32 = 20_000;
+
+// IMU Configuration
+/// MPU-6050 I2C bus frequency in Hz
+pub const IMU_I2C_FREQUENCY: u32 = 400_000;
+
+/// IMU sampling period
+pub const IMU_SAMPLE_MS: u64 = 5;
+
+/// Samples averaged for the gyro bias at boot; the bot must sit still
+pub const IMU_CALIBRATION_SAMPLES: u32 = 400;
+
+/// Straight-line drift correction, `None` to drive without it
+pub const HEADING_HOLD: Option<HeadingHold> = Some(HeadingHold {
+    gain: 50,
+    max_correction: 20,
+});
+
+/// Accelerometer Z beyond this (in milli-g) counts as upright or flipped
+pub const FLIP_THRESHOLD_MG: i16 = 500;
+
+/// How long the bot must read flipped (or upright again) before the drive
+/// inverts, so hits and bounces do not swap the controls
+pub const FLIP_DEBOUNCE_MS: u64 = 500;
+
+/// Button that toggles inverted drive by hand
+pub const INVERT_TOGGLE_BUTTON: u16 = Buttons::TRIANGLE;
+
+// Communication Configuration
+/// Channel buffer sizes
+pub const COMMAND_CHANNEL_SIZE: usize = 8;
+pub const STATUS_CHANNEL_SIZE: usize = 4;
+
+// Timing Configuration
+/// LED blink duration in milliseconds
+pub const LED_BLINK_MS: u64 = 10;
+
+/// Controller timeout in milliseconds
+pub const CONTROLLER_TIMEOUT_MS: u64 = 100;
+
+/// Maximum age of controller data before the link is considered lost.
+/// Must be comfortably longer than `CONTROLLER_TIMEOUT_MS` so a single
+/// read retry does not trip the failsafe.
+pub const LINK_LOSS_TIMEOUT_MS: u64 = 250;
+
+/// How long the link may stay lost before escalating to an emergency
+pub const LINK_LOSS_EMERGENCY_MS: u64 = 3000;
+
+/// Maximum time between heartbeats before the other core is considered
+/// stopped. A quiet input core is handled as link loss; a quiet actuation
+/// core is reported to the driver from core 0.
+pub const HEARTBEAT_TIMEOUT_MS: u64 = 500;
+
+/// Full rumble on entering an emergency, so the driver knows the bot stopped
+pub const EMERGENCY_RUMBLE_MS: u64 = 1000;
+
+/// Receiver LED blink half-periods: fast while core 1 is not responding,
+/// slow while it holds the bot in an emergency
+pub const RECEIVER_LED_FAULT_BLINK_MS: u64 = 100;
+pub const RECEIVER_LED_EMERGENCY_BLINK_MS: u64 = 500;
+
+/// USB console motor test: default run time, and the longest allowed
+pub const MOTOR_TEST_DEFAULT_MS: u64 = 1000;
+pub const MOTOR_TEST_MAX_MS: u64 = 5000;
+
+/// Telemetry stream period; every message is sent each period
+pub const TELEMETRY_PERIOD_MS: u64 = 50;
+
+/// Hardware watchdog timeout; the RP2040 resets if not fed within this
+pub const WATCHDOG_TIMEOUT_MS: u64 = 500;
+
+/// How often the watchdog task checks the heartbeat registry and feeds
+pub const WATCHDOG_FEED_MS: u64 = 100;
+
+/// A watched task that has not checked in for this long stops the feeding
+pub const TASK_HEARTBEAT_TIMEOUT_MS: u32 = 200;
+
+/// How long after boot a watched task may take to check in for the first
+/// time, covering flash and settings loads before the loops start. The
+/// watchdog keeps being fed meanwhile.
+pub const TASK_STARTUP_GRACE_MS: u32 = 2000;
+
+/// Slowest control loop rate and PS2 SPI clock the settings accept
+pub const CONTROL_LOOP_HZ_MIN: u32 = 10;
+pub const PS2_SPI_FREQUENCY_MIN: u32 = 10_000;
+
+/// Longest one PS2 poll blocks the input task: 21 bytes with pressures at
+/// the slowest SPI clock
+pub const PS2_POLL_MAX_MS: u64 = (21 * 8 * 1000u64).div_ceil(PS2_SPI_FREQUENCY_MIN as u64);
+
+/// Longest the input task goes between watchdog check-ins. A source that
+/// paces itself blocks in `read` for up to `CONTROLLER_TIMEOUT_MS` and is
+/// retried straight away. A polled one blocks for a PS2 poll, then waits out
+/// the retry delay or the slowest control loop tick.
+pub const INPUT_LOOP_WORST_CASE_MS: u64 = {
+    let slowest_tick = 1000 / CONTROL_LOOP_HZ_MIN as u64;
+    let polled = PS2_POLL_MAX_MS
+        + if CONTROLLER_TIMEOUT_MS > slowest_tick {
+            CONTROLLER_TIMEOUT_MS
+        } else {
+            slowest_tick
+        };
+    if CONTROLLER_TIMEOUT_MS > polled {
+        CONTROLLER_TIMEOUT_MS
+    } else {
+        polled
+    }
+};
+
+// Keep a quarter of the task heartbeat timeout as margin for a busy executor
+const _: () = assert!(INPUT_LOOP_WORST_CASE_MS * 4 <= TASK_HEARTBEAT_TIMEOUT_MS as u64 * 3);
+
+/// State LED blink code timing
+pub const BLINK_CODE_TICK_MS: u64 = 200;
+pub const BLINK_CODE_PAUSE_TICKS: u16 = 5;
+
+// Blackbox Configuration
+/// Total QSPI flash on the board
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Blackbox region at the end of flash, carved out of `FLASH` in memory.x.
+/// Offsets are from the start of flash, as the flash driver and a full
+/// flash dump both count them.
+pub const BLACKBOX_OFFSET: u32 = 0x1F_0000;
+pub const BLACKBOX_SIZE: u32 = 64 * 1024;
+
+/// How often the blackbox task samples status, battery and current
+pub const BLACKBOX_POLL_MS: u64 = 100;
+
+/// Sector holding the saved `Settings` record, just below the blackbox
+pub const SETTINGS_OFFSET: u32 = 0x1E_F000;
+pub const SETTINGS_SIZE: u32 = 4096;
+
+// Core Configuration
+/// Stack size for Core 1 in bytes
+pub const CORE1_STACK_SIZE: usize = 8192;
+
+// Pin Mapping Documentation
+// Pin assignments for the RP2040
+//
+// PS2 Controller (SPI1):
+// - PIN_12: MISO (Data from controller)
+// - PIN_13: CS/SS (Chip Select)
+// - PIN_14: SCK (Clock)
+// - PIN_15: MOSI (Commands to controller)
+//
+// RC receiver (UART0 RX), `rc-sbus` or `rc-crsf` feature, replacing the PS2 port:
+// - PIN_13: receiver SBUS or CRSF TX
+//
+// RC receiver (PIO0), `rc-ppm` or `rc-pwm` feature, replacing the PS2 port:
+// - PIN_13: PPM stream
+// - PIN_12-15: PWM pulses for the channels in `RC_PWM_CHANNELS`
+//
+// Motor Driver (TB6612FNG), default two-motor layout:
+// - PIN_16: BR PWM (Speed control)
+// - PIN_17: BR IN1 (Direction control)
+// - PIN_18: BR IN2 (Direction control)
+// - PIN_7:  FL PWM
+// - PIN_8/9: FL IN1/IN2 (direction flipped by `MOTOR_CAL_FL`)
+// - PIN_19: STBY (Standby/Enable)
+//
+// Motor Drivers (2x TB6612FNG), `four-motor` feature:
+// - Driver A (right side, STBY PIN_19): BR PWM PIN_16, IN1/IN2 PIN_2/PIN_18
+//                                       FR PWM PIN_17, IN1/IN2 PIN_3/PIN_4
+// - Driver B (left side, STBY PIN_5):   FL PWM PIN_7, IN1/IN2 PIN_8/PIN_9
+//                                       BL PWM PIN_6, IN1/IN2 PIN_10/PIN_11
+//
+// Servo:
+// - PIN_21: PWM signal (PWM slice 2 B, sharing the weapon ESC's 50Hz frame;
+//           the only broken-out pin not already taken in the four-motor layout)
+//
+// Status:
+// - PIN_22: Status LED
+//
+// Analog (ADC):
+// - PIN_26: ADC0, right side current shunt amplifier
+// - PIN_27: ADC1, left side current shunt amplifier
+// - PIN_28: ADC2, battery voltage divider
+//
+// IMU (MPU-6050 on I2C0):
+// - PIN_0: SDA
+// - PIN_1: SCL
+//
+// Weapon:
+// - PIN_20: Spinner ESC signal (PWM slice 2 A)
@@ -0,0 +1,265 @@
+//! USB console command parsing
+//!
+//! The console is a plain line-based text protocol so any serial terminal
+//! works. Parsing lives here so it can be tested on the host; the firmware
+//! only moves bytes and carries out the commands.
+
+use crate::config::{MOTOR_TEST_DEFAULT_MS, MOTOR_TEST_MAX_MS};
+use crate::settings::SettingKey;
+
+/// Shown by `help` and after an unknown command
+pub const HELP: &str = "\
+commands:
+  status                  state, weapon, battery and current
+  input                   latest controller data
+  get [name]              show one setting, or all of them
+  set <name> <value>      change a setting until the next reset
+  save                    write the settings to flash (disarmed only)
+  motor <left|right|both> <speed> [ms]
+                          run the drive at -100..100, idle only
+  stop                    emergency stop
+";
+
+/// Drive side for a motor test
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MotorTest {
+    pub side: Side,
+    /// -100 to 100
+    pub speed: i8,
+    pub duration_ms: u64,
+}
+
+impl MotorTest {
+    /// Left and right speeds for the tank driver
+    pub fn sides(&self) -> (i8, i8) {
+        match self.side {
+            Side::Left => (self.speed, 0),
+            Side::Right => (0, self.speed),
+            Side::Both => (self.speed, self.speed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    Help,
+    Status,
+    Input,
+    /// One setting, or all of them for None
+    Get(Option<SettingKey>),
+    Set(SettingKey, u32),
+    Save,
+    MotorTest(MotorTest),
+    Stop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    MissingArgument,
+    TooManyArguments,
+    BadNumber,
+    UnknownSetting,
+    OutOfRange,
+    UnknownSide,
+    LineTooLong,
+}
+
+impl ParseError {
+    pub fn message(self) -> &'static str {
+        match self {
+            ParseError::Empty => "empty line",
+            ParseError::UnknownCommand => "unknown command, try help",
+            ParseError::MissingArgument => "missing argument",
+            ParseError::TooManyArguments => "too many arguments",
+            ParseError::BadNumber => "not a number",
+            ParseError::UnknownSetting => "unknown setting, try get",
+            ParseError::OutOfRange => "value out of range",
+            ParseError::UnknownSide => "side must be left, right or both",
+            ParseError::LineTooLong => "line too long",
+        }
+    }
+}
+
+/// Parse one line, without its line ending
+pub fn parse(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_ascii_whitespace();
+    let command = match words.next().ok_or(ParseError::Empty)? {
+        "help" | "?" => Command::Help,
+        "status" => Command::Status,
+        "input" => Command::Input,
+        "get" => Command::Get(words.next().map(setting).transpose()?),
+        "set" => {
+            let key = setting(words.next().ok_or(ParseError::MissingArgument)?)?;
+            let value = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::Set(key, value.parse().map_err(|_| ParseError::BadNumber)?)
+        }
+        "save" => Command::Save,
+        "motor" => {
+            let side = match words.next().ok_or(ParseError::MissingArgument)? {
+                "left" | "l" => Side::Left,
+                "right" | "r" => Side::Right,
+                "both" | "b" => Side::Both,
+                _ => return Err(ParseError::UnknownSide),
+            };
+            let speed: i32 = number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            let duration_ms = words.next().map(number).transpose()?.unwrap_or(MOTOR_TEST_DEFAULT_MS);
+            if !(-100..=100).contains(&speed) || duration_ms > MOTOR_TEST_MAX_MS {
+                return Err(ParseError::OutOfRange);
+            }
+            Command::MotorTest(MotorTest {
+                side,
+                speed: speed as i8,
+                duration_ms,
+            })
+        }
+        "stop" => Command::Stop,
+        _ => return Err(ParseError::UnknownCommand),
+    };
+
+    if words.next().is_some() {
+        return Err(ParseError::TooManyArguments);
+    }
+    Ok(command)
+}
+
+fn setting(name: &str) -> Result<SettingKey, ParseError> {
+    SettingKey::from_name(name).ok_or(ParseError::UnknownSetting)
+}
+
+fn number<T: core::str::FromStr>(word: &str) -> Result<T, ParseError> {
+    word.parse().map_err(|_| ParseError::BadNumber)
+}
+
+/// Collects typed bytes into lines
+///
+/// Terminals send `\r`, `\n` or both; any of them ends a line and an empty
+/// line is skipped, so `\r\n` never yields a second, blank command. Backspace
+/// edits the line, and an overlong line is thrown away whole rather than
+/// run truncated.
+pub struct LineBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<const N: usize> LineBuffer<N> {
+    pub const fn new() -> Self {
+        LineBuffer {
+            bytes: [0; N],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Feed one byte; returns the line once it is complete
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, ParseError>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let len = core::mem::take(&mut self.len);
+                if core::mem::take(&mut self.overflowed) {
+                    return Some(Err(ParseError::LineTooLong));
+                }
+                if len == 0 {
+                    return None;
+                }
+                Some(core::str::from_utf8(&self.bytes[..len]).map_err(|_| ParseError::UnknownCommand))
+            }
+            // Backspace and delete
+            0x08 | 0x7F => {
+                self.len = self.len.saturating_sub(1);
+                None
+            }
+            _ if self.len == N => {
+                self.overflowed = true;
+                None
+            }
+            _ => {
+                self.bytes[self.len] = byte;
+                self.len += 1;
+                None
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for LineBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("status"), Ok(Command::Status));
+        assert_eq!(parse("  get  "), Ok(Command::Get(None)));
+        assert_eq!(parse("get spin_dead_zone"), Ok(Command::Get(Some(SettingKey::SpinDeadZone))));
+        assert_eq!(parse("set control_loop_hz 100"), Ok(Command::Set(SettingKey::ControlLoopHz, 100)));
+        assert_eq!(parse("stop"), Ok(Command::Stop));
+    }
+
+    #[test]
+    fn parses_motor_tests() {
+        let test = parse("motor right -40").unwrap();
+        let Command::MotorTest(test) = test else { panic!("{test:?}") };
+        assert_eq!((test.sides(), test.duration_ms), ((0, -40), MOTOR_TEST_DEFAULT_MS));
+
+        assert_eq!(
+            parse("motor both 25 2500"),
+            Ok(Command::MotorTest(MotorTest {
+                side: Side::Both,
+                speed: 25,
+                duration_ms: 2500
+            }))
+        );
+        assert_eq!(parse("motor left 101"), Err(ParseError::OutOfRange));
+        assert_eq!(parse("motor left 50 60000"), Err(ParseError::OutOfRange));
+        assert_eq!(parse("motor left"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("motor up 50"), Err(ParseError::UnknownSide));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(parse(""), Err(ParseError::Empty));
+        assert_eq!(parse("fire"), Err(ParseError::UnknownCommand));
+        assert_eq!(parse("set warp_speed 9"), Err(ParseError::UnknownSetting));
+        assert_eq!(parse("set stick_dead_zone ten"), Err(ParseError::BadNumber));
+        assert_eq!(parse("set stick_dead_zone"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("status now"), Err(ParseError::TooManyArguments));
+    }
+
+    #[test]
+    fn line_buffer_handles_endings_and_editing() {
+        let mut buffer = LineBuffer::<8>::new();
+        let mut lines = Vec::new();
+        for &byte in b"stx\x08op\r\n\rget\n" {
+            if let Some(line) = buffer.push(byte) {
+                lines.push(line.map(|line| line.to_owned()));
+            }
+        }
+        assert_eq!(lines, [Ok("stop".to_owned()), Ok("get".to_owned())]);
+
+        for &byte in b"set stick_dead_zone 9" {
+            assert_eq!(buffer.push(byte), None);
+        }
+        assert_eq!(buffer.push(b'\n'), Some(Err(ParseError::LineTooLong)));
+        assert_eq!(buffer.push(b'x'), None);
+        assert_eq!(buffer.push(b'\r'), Some(Ok("x")));
+    }
+}
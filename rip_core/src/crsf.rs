@@ -0,0 +1,241 @@
+//! CRSF receiver frames, as ExpressLRS and Crossfire receivers send them
+//!
+//! 420 kbaud 8N1, not inverted:
+//!
+//! | bytes | field                                          |
+//! |-------|------------------------------------------------|
+//! | 1     | address, `0xC8` for a flight controller        |
+//! | 1     | length of the type, payload and CRC            |
+//! | 1     | frame type                                     |
+//! | n     | payload                                        |
+//! | 1     | CRC-8/DVB-S2 of the type and payload           |
+//!
+//! Only two types matter here: RC channels, packed like SBUS, and link
+//! statistics, which arrive a few times a second and carry the uplink
+//! quality. CRSF has no failsafe flag; receivers stop sending channels
+//! instead, which the input task sees as a timeout, and report zero link
+//! quality in the meantime.
+
+use crate::crc::crc8_dvb_s2;
+use crate::input::LinkQuality;
+use crate::rc::{unpack_channels, RcDecoder, RcError, RcFrame, CHANNEL_COUNT, PACKED_CHANNELS_SIZE};
+
+/// Addresses a receiver may put at the start of a frame
+const ADDRESSES: [u8; 4] = [0xC8, 0xEA, 0xEC, 0xEE];
+
+const TYPE_LINK_STATISTICS: u8 = 0x14;
+const TYPE_RC_CHANNELS: u8 = 0x16;
+
+const LINK_STATISTICS_SIZE: usize = 10;
+
+/// Largest frame on the wire, address and length included
+pub const MAX_FRAME_SIZE: usize = 64;
+
+/// Uplink half of a link statistics frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkStatistics {
+    /// Signal strength at the receiver's active antenna
+    pub rssi_dbm: i8,
+    /// Share of recent packets received, 0 to 100
+    pub link_quality: u8,
+    pub snr_db: i8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Packet {
+    Channels([u16; CHANNEL_COUNT]),
+    LinkStatistics(LinkStatistics),
+    /// Any other frame type, which the bot ignores
+    Other(u8),
+}
+
+/// Parse one complete frame, address to CRC
+pub fn parse(frame: &[u8]) -> Result<Packet, RcError> {
+    if frame.len() < 4 || !ADDRESSES.contains(&frame[0]) {
+        return Err(RcError::BadFraming);
+    }
+    if frame[1] as usize + 2 != frame.len() {
+        return Err(RcError::BadLength);
+    }
+    let (body, crc) = frame[2..].split_at(frame.len() - 3);
+    if crc8_dvb_s2(body) != crc[0] {
+        return Err(RcError::BadCrc);
+    }
+
+    let (frame_type, payload) = (body[0], &body[1..]);
+    match frame_type {
+        TYPE_RC_CHANNELS => {
+            let packed: &[u8; PACKED_CHANNELS_SIZE] = payload.try_into().map_err(|_| RcError::BadLength)?;
+            Ok(Packet::Channels(unpack_channels(packed)))
+        }
+        TYPE_LINK_STATISTICS => {
+            if payload.len() != LINK_STATISTICS_SIZE {
+                return Err(RcError::BadLength);
+            }
+            // RSSI for both antennas as positive dBm, then LQ, SNR and the
+            // active antenna
+            let rssi = if payload[4] == 0 { payload[0] } else { payload[1] };
+            Ok(Packet::LinkStatistics(LinkStatistics {
+                rssi_dbm: -(rssi.min(127) as i8),
+                link_quality: payload[2],
+                snr_db: payload[3] as i8,
+            }))
+        }
+        other => Ok(Packet::Other(other)),
+    }
+}
+
+/// Reassembles frames from UART bytes, pairing each set of channels with the
+/// latest link statistics
+pub struct CrsfDecoder {
+    buf: [u8; MAX_FRAME_SIZE],
+    len: usize,
+    link: LinkQuality,
+}
+
+impl CrsfDecoder {
+    pub const fn new() -> Self {
+        CrsfDecoder {
+            buf: [0; MAX_FRAME_SIZE],
+            len: 0,
+            // Until the first statistics arrive, channels are all there is
+            link: LinkQuality {
+                percent: 100,
+                rssi_dbm: None,
+                failsafe: false,
+            },
+        }
+    }
+
+    fn discard(&mut self, count: usize) {
+        self.buf.copy_within(count..self.len, 0);
+        self.len -= count;
+    }
+}
+
+impl Default for CrsfDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RcDecoder for CrsfDecoder {
+    fn push(&mut self, byte: u8) {
+        // Only reachable with a garbage length byte, which next_frame drops
+        if self.len == MAX_FRAME_SIZE {
+            self.discard(1);
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    fn next_frame(&mut self) -> Option<Result<RcFrame, RcError>> {
+        loop {
+            while self.len > 0 && !ADDRESSES.contains(&self.buf[0]) {
+                self.discard(1);
+            }
+            if self.len < 2 {
+                return None;
+            }
+            let total = self.buf[1] as usize + 2;
+            if !(4..=MAX_FRAME_SIZE).contains(&total) {
+                self.discard(1);
+                return Some(Err(RcError::BadLength));
+            }
+            if self.len < total {
+                return None;
+            }
+
+            let result = parse(&self.buf[..total]);
+            // A good frame is consumed whole; a bad one might hide a real
+            // frame's address byte, so only step past its first byte
+            self.discard(if result.is_ok() { total } else { 1 });
+            match result {
+                Ok(Packet::Channels(channels)) => return Some(Ok(RcFrame { channels, link: self.link })),
+                Ok(Packet::LinkStatistics(stats)) => {
+                    self.link = LinkQuality {
+                        percent: stats.link_quality.min(100),
+                        rssi_dbm: Some(stats.rssi_dbm),
+                        failsafe: stats.link_quality == 0,
+                    };
+                }
+                Ok(Packet::Other(_)) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ELRS receiver's channels frame: sticks centred, channel 2 full
+    /// up, channel 3 and switches 6 and 7 at the bottom, switch 5 on
+    const CHANNELS_FRAME: [u8; 26] = [
+        0xC8, 0x18, 0x16, 0xE0, 0x9B, 0x38, 0x2B, 0xC0, 0x37, 0x71, 0x56, 0xB0, 0x02, 0x7C, 0xE0, 0x03, 0x1F,
+        0xF8, 0xC0, 0x07, 0x3E, 0xF0, 0x81, 0x0F, 0x7C, 0x7D,
+    ];
+    const CHANNELS: [u16; 16] = [
+        992, 1811, 172, 992, 1811, 172, 172, 992, 992, 992, 992, 992, 992, 992, 992, 992,
+    ];
+
+    /// Link statistics: -60 and -64 dBm, LQ 100, SNR 9, antenna 1
+    const STATS_FRAME: [u8; 14] = [0xC8, 0x0C, 0x14, 0x3C, 0x40, 0x64, 0x09, 0x00, 0x04, 0x02, 0x32, 0x64, 0x07, 0xC1];
+
+    /// Link statistics once the transmitter has gone: LQ 0
+    const LOST_FRAME: [u8; 14] = [0xC8, 0x0C, 0x14, 0x3C, 0x40, 0x00, 0xF6, 0x00, 0x04, 0x02, 0x32, 0x00, 0x07, 0xA8];
+
+    fn decode(decoder: &mut CrsfDecoder, bytes: &[u8]) -> Vec<Result<RcFrame, RcError>> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            decoder.push(byte);
+            while let Some(frame) = decoder.next_frame() {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn parses_recorded_frames() {
+        assert_eq!(parse(&CHANNELS_FRAME), Ok(Packet::Channels(CHANNELS)));
+        assert_eq!(
+            parse(&STATS_FRAME),
+            Ok(Packet::LinkStatistics(LinkStatistics {
+                rssi_dbm: -60,
+                link_quality: 100,
+                snr_db: 9
+            }))
+        );
+
+        let mut corrupt = CHANNELS_FRAME;
+        corrupt[10] ^= 0x01;
+        assert_eq!(parse(&corrupt), Err(RcError::BadCrc));
+        assert_eq!(parse(&CHANNELS_FRAME[..20]), Err(RcError::BadLength));
+    }
+
+    #[test]
+    fn pairs_channels_with_link_statistics() {
+        // Joined mid-frame, with line noise between frames
+        let mut stream = CHANNELS_FRAME[7..].to_vec();
+        stream.extend_from_slice(&CHANNELS_FRAME);
+        stream.extend_from_slice(&[0x00, 0xFF]);
+        stream.extend_from_slice(&STATS_FRAME);
+        stream.extend_from_slice(&CHANNELS_FRAME);
+        stream.extend_from_slice(&LOST_FRAME);
+        stream.extend_from_slice(&CHANNELS_FRAME);
+
+        let mut decoder = CrsfDecoder::new();
+        let frames: Vec<_> = decode(&mut decoder, &stream).into_iter().filter_map(Result::ok).collect();
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|f| f.channels == CHANNELS));
+        assert_eq!(frames[0].link.rssi_dbm, None);
+        assert_eq!(frames[1].link.rssi_dbm, Some(-60));
+        assert_eq!(frames[1].link.percent, 100);
+        assert!(!frames[1].link.failsafe);
+        assert!(frames[2].link.failsafe);
+    }
+}
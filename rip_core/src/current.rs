@@ -0,0 +1,160 @@
+//! Motor current sensing: conversion, duty derating and stall detection
+//!
+//! Each drive side has a shunt amplifier on an ADC pin. Over a limit the
+//! side's duty is scaled back a step per sample; a side that stays over its
+//! limits for `stall_ms` counts as stalled so the drive can be disabled
+//! before the driver overheats.
+
+/// Shunt amplifier scaling and current limits for one drive side
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CurrentConfig {
+    /// Amplifier output in millivolts per amp (gain times shunt resistance)
+    pub mv_per_amp: u32,
+    /// ADC reference voltage in millivolts
+    pub adc_ref_mv: u32,
+    /// Limit on any single reading
+    pub peak_limit_ma: u32,
+    /// Limit on the filtered average, roughly the driver's continuous rating
+    pub average_limit_ma: u32,
+    /// Average filter strength: each sample moves the average 1/2^shift
+    pub average_shift: u8,
+    /// Duty limit removed per sample while over a limit, in percent
+    pub derate_step: u8,
+    /// Duty limit restored per sample once back under the limits
+    pub recover_step: u8,
+    /// Derating never goes below this duty limit
+    pub min_duty: u8,
+    /// Time over the limits after which the side counts as stalled
+    pub stall_ms: u32,
+}
+
+impl CurrentConfig {
+    /// Current in milliamps for a raw 12-bit ADC reading
+    pub fn adc_to_milliamps(&self, raw: u16) -> u32 {
+        let mv = raw.min(4095) as u32 * self.adc_ref_mv / 4095;
+        mv * 1000 / self.mv_per_amp
+    }
+}
+
+/// Latest reading for one side
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CurrentStatus {
+    pub milliamps: u32,
+    pub average_ma: u32,
+    /// Duty limit for the side in percent; 100 means not derated
+    pub duty_limit: u8,
+    pub stalled: bool,
+}
+
+/// Both sides' readings, as published to the drive and telemetry
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriveCurrent {
+    pub left: CurrentStatus,
+    pub right: CurrentStatus,
+}
+
+/// Tracks one side against its peak and average limits
+pub struct CurrentLimiter {
+    config: CurrentConfig,
+    /// Average current in milliamps, scaled by 2^average_shift
+    average: u32,
+    duty_limit: u8,
+    over_ms: u32,
+}
+
+impl CurrentLimiter {
+    pub const fn new(config: CurrentConfig) -> Self {
+        CurrentLimiter {
+            config,
+            average: 0,
+            duty_limit: 100,
+            over_ms: 0,
+        }
+    }
+
+    /// Feed one raw ADC sample taken `dt_ms` after the previous one
+    pub fn update(&mut self, raw: u16, dt_ms: u32) -> CurrentStatus {
+        let config = &self.config;
+        let milliamps = config.adc_to_milliamps(raw);
+        let shift = config.average_shift;
+
+        // Starts from zero: the motors are idle at boot
+        self.average = self.average - (self.average >> shift) + milliamps;
+        let average_ma = self.average >> shift;
+
+        let over = milliamps > config.peak_limit_ma || average_ma > config.average_limit_ma;
+        if over {
+            self.duty_limit = self
+                .duty_limit
+                .saturating_sub(config.derate_step)
+                .max(config.min_duty);
+            self.over_ms = self.over_ms.saturating_add(dt_ms);
+        } else {
+            self.duty_limit = self.duty_limit.saturating_add(config.recover_step).min(100);
+            self.over_ms = 0;
+        }
+
+        CurrentStatus {
+            milliamps,
+            average_ma,
+            duty_limit: self.duty_limit,
+            stalled: self.over_ms >= config.stall_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 count = 1 mV = 1 mA
+    const CONFIG: CurrentConfig = CurrentConfig {
+        mv_per_amp: 1000,
+        adc_ref_mv: 4095,
+        peak_limit_ma: 3000,
+        average_limit_ma: 1000,
+        average_shift: 2,
+        derate_step: 10,
+        recover_step: 5,
+        min_duty: 40,
+        stall_ms: 50,
+    };
+
+    #[test]
+    fn converts_amplifier_output() {
+        let config = CurrentConfig { mv_per_amp: 500, adc_ref_mv: 3300, ..CONFIG };
+        assert_eq!(config.adc_to_milliamps(0), 0);
+        assert_eq!(config.adc_to_milliamps(4095), 6600);
+    }
+
+    #[test]
+    fn peak_derates_then_recovers() {
+        let mut limiter = CurrentLimiter::new(CONFIG);
+        assert_eq!(limiter.update(3500, 5).duty_limit, 90);
+        assert_eq!(limiter.update(3500, 5).duty_limit, 80);
+
+        for _ in 0..10 {
+            limiter.update(0, 5);
+        }
+        let status = limiter.update(0, 5);
+        assert_eq!(status.duty_limit, 100);
+        assert!(!status.stalled);
+    }
+
+    #[test]
+    fn sustained_overload_stalls_at_min_duty() {
+        let mut limiter = CurrentLimiter::new(CONFIG);
+        let mut status = limiter.update(1500, 10);
+        assert!(!status.stalled);
+        for _ in 0..9 {
+            status = limiter.update(1500, 10);
+        }
+        // Average passes 1000 mA on the fourth sample, stalled 50 ms later
+        assert_eq!(status.duty_limit, 40);
+        assert!(status.stalled);
+        assert!(status.average_ma > CONFIG.average_limit_ma);
+    }
+}
@@ -0,0 +1,110 @@
+//! Event types for Core 1 hardware control
+
+/// Events for controlling the tank drive motors
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TankDriveEvent {
+    /// Drive with x/y coordinates (-100 to 100)
+    Move { x: i8, y: i8 },
+    /// Spin in place (-100 to 100)
+    Spin(i8),
+    /// Stop all motors
+    Stop,
+    /// Enable motor drivers (after emergency)
+    Enable,
+    /// Disable motor drivers (for emergency)
+    Disable,
+    /// Drive upside down (true) or right way up (false)
+    SetInverted(bool),
+    /// Run each side directly (-100 to 100), for bench tests
+    Sides { left: i8, right: i8 },
+}
+
+/// Events for controlling the servo
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ServoEvent {
+    /// Set servo angle (0-180 degrees)
+    SetAngle(u8),
+}
+
+/// Events for controlling the spinner weapon
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WeaponEvent {
+    /// Ramp to a throttle (0 to 100)
+    Spin(u8),
+    /// Ramp down to a stop
+    Stop,
+    /// Cut throttle immediately (for emergency)
+    Kill,
+}
+
+/// Events for LED state indication
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LedEvent {
+    /// Turn LED off
+    Off,
+    /// Slow blink pattern
+    SlowBlink,
+    /// Fast blink pattern
+    FastBlink,
+    /// Solid on
+    Solid,
+    /// Repeating group of short flashes followed by a pause
+    BlinkCode(u8),
+    /// Long on, short off: attention needed but still running (low battery)
+    Warning,
+    /// Two short flashes then a pause: driving upside down
+    Inverted,
+    /// Arming countdown with this many seconds left: one flash per second
+    Countdown(u8),
+}
+
+/// Why the bot entered `BotState::Emergency`
+///
+/// The discriminant doubles as the LED blink code, so keep them stable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum EmergencyReason {
+    /// Driver pressed L1+R1+L2+R2 together
+    ControllerCombo = 1,
+    /// Controller link stayed lost past `LINK_LOSS_EMERGENCY_MS`
+    LinkLossTimeout = 2,
+    /// A motor current reading exceeded its limit
+    OverCurrent = 3,
+    /// A core panicked, either this boot or just before the last reset
+    CorePanic = 4,
+    /// The input core heartbeat stayed missing past `LINK_LOSS_EMERGENCY_MS`
+    MissedHeartbeat = 5,
+    /// Battery fell below the hard LiPo cutoff
+    LowBattery = 6,
+    /// The last reset was the hardware watchdog firing
+    WatchdogReset = 7,
+    /// Emergency stop typed on the USB console
+    ConsoleStop = 8,
+}
+
+impl EmergencyReason {
+    /// Number of flashes used to show this reason on the state LED
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Inverse of [`EmergencyReason::code`], for reasons read back from storage
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            1 => EmergencyReason::ControllerCombo,
+            2 => EmergencyReason::LinkLossTimeout,
+            3 => EmergencyReason::OverCurrent,
+            4 => EmergencyReason::CorePanic,
+            5 => EmergencyReason::MissedHeartbeat,
+            6 => EmergencyReason::LowBattery,
+            7 => EmergencyReason::WatchdogReset,
+            8 => EmergencyReason::ConsoleStop,
+            _ => return None,
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,338 @@
+//! MPU-6050 IMU driver, gyro bias calibration and orientation tracking
+//!
+//! The driver is generic over an async `embedded-hal` I2C bus. Gyro readings
+//! are converted to millidegrees per second and accelerometer readings to
+//! milli-g; [`OrientationTracker`] integrates the bias-corrected yaw rate
+//! into a heading.
+
+use embedded_hal_async::i2c::I2c;
+
+/// Default address, AD0 tied low
+pub const MPU6050_ADDRESS: u8 = 0x68;
+
+const WHO_AM_I_VALUE: u8 = 0x68;
+
+mod reg {
+    pub const SMPLRT_DIV: u8 = 0x19;
+    pub const CONFIG: u8 = 0x1A;
+    pub const GYRO_CONFIG: u8 = 0x1B;
+    pub const ACCEL_CONFIG: u8 = 0x1C;
+    pub const ACCEL_XOUT_H: u8 = 0x3B;
+    pub const PWR_MGMT_1: u8 = 0x6B;
+    pub const WHO_AM_I: u8 = 0x75;
+}
+
+/// Gyro at +/-500 deg/s: 65.5 LSB per deg/s
+const GYRO_MDPS_NUM: i32 = 2000;
+const GYRO_MDPS_DEN: i32 = 131;
+/// Accelerometer at +/-2 g: 16384 LSB per g
+const ACCEL_LSB_PER_G: i32 = 16384;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ImuError {
+    /// An I2C transfer failed
+    Bus,
+    /// Something other than an MPU-6050 answered; holds its WHO_AM_I value
+    WrongDevice(u8),
+}
+
+/// One reading, in body axes (x forward, y left, z up)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ImuSample {
+    pub accel_mg: [i16; 3],
+    pub gyro_mdps: [i32; 3],
+}
+
+impl ImuSample {
+    /// Decode the 14-byte burst starting at ACCEL_XOUT_H
+    pub fn from_registers(buf: &[u8; 14]) -> Self {
+        let word = |i: usize| i16::from_be_bytes([buf[i], buf[i + 1]]) as i32;
+        let mut sample = ImuSample::default();
+        for axis in 0..3 {
+            sample.accel_mg[axis] = (word(axis * 2) * 1000 / ACCEL_LSB_PER_G) as i16;
+            // Bytes 6 and 7 are the temperature
+            sample.gyro_mdps[axis] = word(8 + axis * 2) * GYRO_MDPS_NUM / GYRO_MDPS_DEN;
+        }
+        sample
+    }
+}
+
+pub struct Mpu6050<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> Mpu6050<I2C> {
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Mpu6050 { i2c, address }
+    }
+
+    /// Check the part, wake it up and set ranges and filtering
+    pub async fn init(&mut self) -> Result<(), ImuError> {
+        let id = self.read_register(reg::WHO_AM_I).await?;
+        if id != WHO_AM_I_VALUE {
+            return Err(ImuError::WrongDevice(id));
+        }
+
+        // Wake up with the X gyro as clock source
+        self.write_register(reg::PWR_MGMT_1, 0x01).await?;
+        // 44 Hz low-pass filter, 1 kHz internal rate divided down to 200 Hz
+        self.write_register(reg::CONFIG, 0x03).await?;
+        self.write_register(reg::SMPLRT_DIV, 4).await?;
+        // +/-500 deg/s and +/-2 g
+        self.write_register(reg::GYRO_CONFIG, 0x08).await?;
+        self.write_register(reg::ACCEL_CONFIG, 0x00).await
+    }
+
+    pub async fn read(&mut self) -> Result<ImuSample, ImuError> {
+        let mut buf = [0; 14];
+        self.i2c
+            .write_read(self.address, &[reg::ACCEL_XOUT_H], &mut buf)
+            .await
+            .map_err(|_| ImuError::Bus)?;
+        Ok(ImuSample::from_registers(&buf))
+    }
+
+    async fn read_register(&mut self, register: u8) -> Result<u8, ImuError> {
+        let mut value = [0];
+        self.i2c
+            .write_read(self.address, &[register], &mut value)
+            .await
+            .map_err(|_| ImuError::Bus)?;
+        Ok(value[0])
+    }
+
+    async fn write_register(&mut self, register: u8, value: u8) -> Result<(), ImuError> {
+        self.i2c
+            .write(self.address, &[register, value])
+            .await
+            .map_err(|_| ImuError::Bus)
+    }
+}
+
+/// Averages gyro readings taken while the bot sits still
+#[derive(Default)]
+pub struct GyroCalibration {
+    sum: [i64; 3],
+    count: u32,
+}
+
+impl GyroCalibration {
+    pub const fn new() -> Self {
+        GyroCalibration { sum: [0; 3], count: 0 }
+    }
+
+    pub fn add(&mut self, sample: &ImuSample) {
+        for axis in 0..3 {
+            self.sum[axis] += sample.gyro_mdps[axis] as i64;
+        }
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Mean rate per axis, to subtract from later readings
+    pub fn bias(&self) -> [i32; 3] {
+        let count = self.count.max(1) as i64;
+        self.sum.map(|sum| (sum / count) as i32)
+    }
+}
+
+/// Published orientation estimate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Orientation {
+    /// Heading since boot in millidegrees, -180000 to 179999, CCW positive
+    pub heading_mdeg: i32,
+    /// Bias-corrected yaw rate in millidegrees per second, CCW positive
+    pub yaw_rate_mdps: i32,
+    /// Raw accelerometer, for tilt and flip detection
+    pub accel_mg: [i16; 3],
+}
+
+/// Integrates yaw rate into a heading
+pub struct OrientationTracker {
+    bias: [i32; 3],
+    /// Heading in microdegrees, to keep small rates from rounding away
+    heading_udeg: i64,
+}
+
+impl OrientationTracker {
+    pub const fn new(bias: [i32; 3]) -> Self {
+        OrientationTracker { bias, heading_udeg: 0 }
+    }
+
+    /// Feed one sample taken `dt_us` after the previous one
+    pub fn update(&mut self, sample: &ImuSample, dt_us: u32) -> Orientation {
+        const FULL_TURN_UDEG: i64 = 360_000_000;
+
+        let yaw_rate_mdps = sample.gyro_mdps[2] - self.bias[2];
+        self.heading_udeg += yaw_rate_mdps as i64 * dt_us as i64 / 1000;
+        self.heading_udeg = (self.heading_udeg + FULL_TURN_UDEG / 2).rem_euclid(FULL_TURN_UDEG)
+            - FULL_TURN_UDEG / 2;
+
+        Orientation {
+            heading_mdeg: (self.heading_udeg / 1000) as i32,
+            yaw_rate_mdps,
+            accel_mg: sample.accel_mg,
+        }
+    }
+}
+
+/// Debounced upside-down detection from the accelerometer Z axis
+///
+/// Readings between the thresholds (on its side, mid-tumble) keep the current
+/// orientation; a new one has to hold for the whole debounce window.
+pub struct FlipDetector {
+    threshold_mg: i16,
+    debounce_ms: u64,
+    flipped: bool,
+    /// When readings started disagreeing with `flipped`
+    since_ms: Option<u64>,
+}
+
+impl FlipDetector {
+    pub const fn new(threshold_mg: i16, debounce_ms: u64) -> Self {
+        FlipDetector {
+            threshold_mg,
+            debounce_ms,
+            flipped: false,
+            since_ms: None,
+        }
+    }
+
+    pub fn flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Feed a Z reading; returns the new orientation when it changes
+    pub fn update(&mut self, accel_z_mg: i16, now_ms: u64) -> Option<bool> {
+        let disagrees = if self.flipped {
+            accel_z_mg > self.threshold_mg
+        } else {
+            accel_z_mg < -self.threshold_mg
+        };
+        if !disagrees {
+            self.since_ms = None;
+            return None;
+        }
+
+        let since = *self.since_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(since) < self.debounce_ms {
+            return None;
+        }
+        self.flipped = !self.flipped;
+        self.since_ms = None;
+        Some(self.flipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embassy_futures::block_on;
+    use embedded_hal_async::i2c::{ErrorType, Operation};
+
+    /// Register file behind an auto-incrementing pointer, like the real part
+    struct MockI2c {
+        regs: [u8; 128],
+    }
+
+    impl ErrorType for MockI2c {
+        type Error = Infallible;
+    }
+
+    impl I2c for MockI2c {
+        async fn transaction(
+            &mut self,
+            _address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Infallible> {
+            let mut pointer = 0;
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => {
+                        pointer = bytes[0] as usize;
+                        for &byte in &bytes[1..] {
+                            self.regs[pointer] = byte;
+                            pointer += 1;
+                        }
+                    }
+                    Operation::Read(buf) => {
+                        for byte in buf.iter_mut() {
+                            *byte = self.regs[pointer];
+                            pointer += 1;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn init_checks_identity_and_configures() {
+        let mut regs = [0; 128];
+        regs[reg::WHO_AM_I as usize] = WHO_AM_I_VALUE;
+        let mut imu = Mpu6050::new(MockI2c { regs }, MPU6050_ADDRESS);
+        block_on(imu.init()).unwrap();
+        assert_eq!(imu.i2c.regs[reg::PWR_MGMT_1 as usize], 0x01);
+        assert_eq!(imu.i2c.regs[reg::GYRO_CONFIG as usize], 0x08);
+
+        imu.i2c.regs[reg::WHO_AM_I as usize] = 0x70;
+        assert_eq!(block_on(imu.init()), Err(ImuError::WrongDevice(0x70)));
+    }
+
+    #[test]
+    fn read_scales_burst() {
+        let mut regs = [0; 128];
+        let base = reg::ACCEL_XOUT_H as usize;
+        // 1 g on z, -131 LSB (-2 deg/s) on gyro z
+        regs[base + 4..base + 6].copy_from_slice(&16384i16.to_be_bytes());
+        regs[base + 12..base + 14].copy_from_slice(&(-131i16).to_be_bytes());
+        let mut imu = Mpu6050::new(MockI2c { regs }, MPU6050_ADDRESS);
+
+        let sample = block_on(imu.read()).unwrap();
+        assert_eq!(sample.accel_mg, [0, 0, 1000]);
+        assert_eq!(sample.gyro_mdps, [0, 0, -2000]);
+    }
+
+    #[test]
+    fn flip_detector_debounces() {
+        let mut flip = FlipDetector::new(500, 300);
+        assert_eq!(flip.update(-1000, 0), None);
+        assert_eq!(flip.update(-1000, 200), None);
+        // A sideways reading restarts the window
+        assert_eq!(flip.update(0, 250), None);
+        assert_eq!(flip.update(-1000, 300), None);
+        assert_eq!(flip.update(-1000, 600), Some(true));
+        assert!(flip.flipped());
+
+        assert_eq!(flip.update(-1000, 700), None);
+        assert_eq!(flip.update(900, 800), None);
+        assert_eq!(flip.update(900, 1100), Some(false));
+    }
+
+    #[test]
+    fn tracker_removes_bias_and_wraps_heading() {
+        let mut calibration = GyroCalibration::new();
+        for rate in [400, 600] {
+            calibration.add(&ImuSample { gyro_mdps: [0, 0, rate], ..ImuSample::default() });
+        }
+        assert_eq!(calibration.bias(), [0, 0, 500]);
+
+        let mut tracker = OrientationTracker::new(calibration.bias());
+        let turning = ImuSample { gyro_mdps: [0, 0, 90_500], ..ImuSample::default() };
+        let orientation = tracker.update(&turning, 1_000_000);
+        assert_eq!(orientation.yaw_rate_mdps, 90_000);
+        assert_eq!(orientation.heading_mdeg, 90_000);
+
+        tracker.update(&turning, 1_000_000);
+        assert_eq!(tracker.update(&turning, 500_000).heading_mdeg, -135_000);
+    }
+}
@@ -0,0 +1,337 @@
+//! Normalized controller frames, input sources and stick processing
+//!
+//! Every receiver, wired PS2 pad or RC radio, is an [`InputSource`] that
+//! turns its own format into a [`ControllerData`] frame. Everything after
+//! that, from the state machine to telemetry, only sees the normalized frame.
+
+use crate::events::TankDriveEvent;
+use crate::settings::Settings;
+
+/// Pressed-button set, active high
+///
+/// Names follow the PS2 pad the bot was built around; other sources map
+/// their own buttons or switches onto these, so the rest of the system never
+/// depends on a particular controller crate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Buttons(u16);
+
+impl Buttons {
+    pub const SELECT: u16 = 1 << 0;
+    pub const L3: u16 = 1 << 1;
+    pub const R3: u16 = 1 << 2;
+    pub const START: u16 = 1 << 3;
+    pub const UP: u16 = 1 << 4;
+    pub const RIGHT: u16 = 1 << 5;
+    pub const DOWN: u16 = 1 << 6;
+    pub const LEFT: u16 = 1 << 7;
+    pub const L2: u16 = 1 << 8;
+    pub const R2: u16 = 1 << 9;
+    pub const L1: u16 = 1 << 10;
+    pub const R1: u16 = 1 << 11;
+    pub const TRIANGLE: u16 = 1 << 12;
+    pub const CIRCLE: u16 = 1 << 13;
+    pub const CROSS: u16 = 1 << 14;
+    pub const SQUARE: u16 = 1 << 15;
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Buttons(bits)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Set or clear a button, for building the set from a receiver
+    pub fn set(&mut self, mask: u16, pressed: bool) {
+        if pressed {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+    }
+
+    /// True if every button in `mask` is pressed
+    pub const fn all(self, mask: u16) -> bool {
+        self.0 & mask == mask
+    }
+
+    pub const fn select(self) -> bool {
+        self.all(Self::SELECT)
+    }
+
+    pub const fn start(self) -> bool {
+        self.all(Self::START)
+    }
+
+    pub const fn l1(self) -> bool {
+        self.all(Self::L1)
+    }
+
+    pub const fn r1(self) -> bool {
+        self.all(Self::R1)
+    }
+
+    pub const fn l2(self) -> bool {
+        self.all(Self::L2)
+    }
+
+    pub const fn r2(self) -> bool {
+        self.all(Self::R2)
+    }
+}
+
+/// How well the receiver hears the transmitter
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkQuality {
+    /// Share of recent packets received, 0 to 100
+    pub percent: u8,
+    /// Signal strength, for radios that report it
+    pub rssi_dbm: Option<i8>,
+    /// The receiver lost the transmitter and is repeating its failsafe
+    /// values; such frames count as no frame at all
+    pub failsafe: bool,
+}
+
+impl LinkQuality {
+    /// A cable: every frame that arrives is a good one
+    pub const WIRED: Self = LinkQuality {
+        percent: 100,
+        rssi_dbm: None,
+        failsafe: false,
+    };
+}
+
+/// One normalized frame from whichever receiver is fitted
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControllerData {
+    /// Sticks, -127 to 127: positive is right on X and up (forward) on Y
+    pub left_x: i8,
+    pub left_y: i8,
+    pub right_x: i8,
+    pub right_y: i8,
+    /// Analog triggers, 0 released to 255 fully pressed
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    pub buttons: Buttons,
+    pub link: LinkQuality,
+    pub timestamp_ms: u64,  // Uptime when the frame was read, used for link-loss detection
+    pub sequence: u32,  // Incremented by the reader for every frame, to spot dropped samples
+}
+
+impl ControllerData {
+    /// Sticks centred, nothing pressed
+    pub const fn neutral(timestamp_ms: u64) -> Self {
+        ControllerData {
+            left_x: 0,
+            left_y: 0,
+            right_x: 0,
+            right_y: 0,
+            left_trigger: 0,
+            right_trigger: 0,
+            buttons: Buttons::from_bits(0),
+            link: LinkQuality::WIRED,
+            timestamp_ms,
+            sequence: 0,
+        }
+    }
+}
+
+/// Signed axis from a reading centred on 128, as PS2 sticks report
+pub const fn centred_axis(raw: u8) -> i8 {
+    let value = raw as i16 - 128;
+    if value < -127 {
+        -127
+    } else {
+        value as i8
+    }
+}
+
+/// Controller rumble motors, for sources that have them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rumble {
+    /// Small motor, on or off
+    pub small: bool,
+    /// Big motor strength, 0 to 255
+    pub big: u8,
+}
+
+/// Why a source produced no frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InputError {
+    /// Nothing answered, or no data arrived in time
+    NoResponse,
+    /// Something answered that this source cannot use
+    Unsupported,
+    /// A frame arrived but failed its checks
+    Corrupt,
+}
+
+/// A receiver the input task can read frames from
+///
+/// Sources fill in sticks, triggers, buttons and link quality; the input
+/// task stamps each frame with its uptime and sequence number. `read` may
+/// block the executor for a short bus transfer, as the PS2 port does.
+// Only ever used from single-threaded executors, so no Send bound needed
+#[allow(async_fn_in_trait)]
+pub trait InputSource {
+    /// Wait for the next frame
+    async fn read(&mut self) -> Result<ControllerData, InputError>;
+
+    /// True when `read` waits on the receiver, and a failed `read` has
+    /// already waited out `CONTROLLER_TIMEOUT_MS`. The input task then
+    /// neither polls on its own ticker nor waits again before retrying.
+    const PACES_ITSELF: bool = false;
+
+    /// Rumble to send with the next read; ignored by sources without it
+    fn set_rumble(&mut self, _rumble: Rumble) {}
+}
+
+/// Counts samples the consumer never saw, from gaps in sequence numbers
+///
+/// The input handoff is latest-value-wins, so a slow consumer silently skips
+/// frames; this makes the skipping visible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SampleTracker {
+    last: Option<u32>,
+    /// Total samples skipped so far
+    pub dropped: u32,
+}
+
+impl SampleTracker {
+    pub const fn new() -> Self {
+        SampleTracker { last: None, dropped: 0 }
+    }
+
+    /// Note a received sequence number and return how many were skipped
+    /// since the previous one
+    pub fn observe(&mut self, sequence: u32) -> u32 {
+        let skipped = match self.last {
+            // A backwards jump means the producer restarted, not a gap
+            Some(last) if sequence.wrapping_sub(last) <= u32::MAX / 2 => {
+                sequence.wrapping_sub(last).saturating_sub(1)
+            }
+            _ => 0,
+        };
+        self.last = Some(sequence);
+        self.dropped = self.dropped.wrapping_add(skipped);
+        skipped
+    }
+}
+
+/// Convert controller sticks into a tank drive event
+pub fn process_movement(data: &ControllerData, settings: &Settings) -> TankDriveEvent {
+    let dead_zone = settings.stick_dead_zone as i16;
+    let x_raw = data.left_x as i16;
+    let y_raw = data.left_y as i16;
+
+    let x = if x_raw.abs() < dead_zone {
+        0
+    } else {
+        (x_raw * 100 / 127) as i8
+    };
+    let y = if y_raw.abs() < dead_zone {
+        0
+    } else {
+        (y_raw * 100 / 127) as i8
+    };
+
+    let rx_raw = data.right_x as i16;
+    let spin = if rx_raw.abs() > settings.spin_dead_zone as i16 {
+        (rx_raw * 100 / 127) as i8
+    } else {
+        0
+    };
+
+    if spin != 0 {
+        TankDriveEvent::Spin(spin)
+    } else if x == 0 && y == 0 {
+        TankDriveEvent::Stop
+    } else {
+        TankDriveEvent::Move { x, y }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sticks(lx: i8, ly: i8, rx: i8) -> ControllerData {
+        ControllerData {
+            left_x: lx,
+            left_y: ly,
+            right_x: rx,
+            ..ControllerData::neutral(0)
+        }
+    }
+
+    #[test]
+    fn centred_sticks_stop() {
+        assert_eq!(process_movement(&sticks(0, 0, 0), &Settings::DEFAULT), TankDriveEvent::Stop);
+        assert_eq!(process_movement(&sticks(7, 7, 19), &Settings::DEFAULT), TankDriveEvent::Stop);
+    }
+
+    #[test]
+    fn full_deflection_maps_to_full_speed() {
+        assert_eq!(
+            process_movement(&sticks(0, 127, 0), &Settings::DEFAULT),
+            TankDriveEvent::Move { x: 0, y: 100 }
+        );
+        assert_eq!(
+            process_movement(&sticks(-127, -127, 0), &Settings::DEFAULT),
+            TankDriveEvent::Move { x: -100, y: -100 }
+        );
+    }
+
+    #[test]
+    fn centred_axes_are_symmetric() {
+        assert_eq!(centred_axis(128), 0);
+        assert_eq!(centred_axis(255), 127);
+        assert_eq!(centred_axis(0), -127);
+        assert_eq!(centred_axis(1), -127);
+    }
+
+    #[test]
+    fn dead_zones_come_from_settings() {
+        let settings = Settings {
+            stick_dead_zone: 30,
+            spin_dead_zone: 5,
+            ..Settings::DEFAULT
+        };
+        assert_eq!(process_movement(&sticks(22, 28, 0), &settings), TankDriveEvent::Stop);
+        assert_eq!(process_movement(&sticks(0, 0, 12), &settings), TankDriveEvent::Spin(9));
+    }
+
+    #[test]
+    fn right_stick_spin_takes_priority() {
+        assert_eq!(process_movement(&sticks(0, 127, 127), &Settings::DEFAULT), TankDriveEvent::Spin(100));
+        assert_eq!(process_movement(&sticks(0, 0, -127), &Settings::DEFAULT), TankDriveEvent::Spin(-100));
+    }
+
+    #[test]
+    fn sample_tracker_counts_gaps() {
+        let mut tracker = SampleTracker::new();
+        assert_eq!(tracker.observe(7), 0);
+        assert_eq!(tracker.observe(8), 0);
+        assert_eq!(tracker.observe(12), 3);
+        assert_eq!(tracker.observe(12), 0);
+        assert_eq!(tracker.observe(1), 0);
+        assert_eq!(tracker.observe(u32::MAX), 0);
+        assert_eq!(tracker.observe(1), 1);
+        assert_eq!(tracker.dropped, 4);
+    }
+
+    #[test]
+    fn button_helpers_match_masks() {
+        let mut buttons = Buttons::default();
+        buttons.set(Buttons::L1 | Buttons::R2, true);
+        assert!(buttons.l1() && buttons.r2());
+        assert!(!buttons.start());
+        buttons.set(Buttons::R2, false);
+        assert_eq!(buttons.bits(), Buttons::L1);
+    }
+}
@@ -0,0 +1,36 @@
+//! Hardware-agnostic core of the battle bot
+//!
+//! Everything in here is plain `no_std` logic with no dependency on the
+//! RP2040 HAL: the bot state machine, controller input processing, drive
+//! mixing and the event types passed between tasks. The firmware crate wires
+//! it to real peripherals; on a host it builds and runs `cargo test` as-is.
+
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+mod fmt;
+
+pub mod battery;
+pub mod blackbox;
+pub mod config;
+pub mod console;
+mod crc;
+pub mod crsf;
+pub mod current;
+pub mod events;
+pub mod imu;
+pub mod input;
+pub mod mixing;
+pub mod motor;
+pub mod panic_record;
+pub mod pulse;
+pub mod rc;
+pub mod sbus;
+pub mod settings;
+pub mod slew;
+pub mod state;
+pub mod tank_drive;
+pub mod telemetry;
+pub mod timing;
+pub mod watchdog;
+pub mod weapon;
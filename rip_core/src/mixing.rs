@@ -0,0 +1,73 @@
+//! Differential drive mixing
+//!
+//! Pure functions that turn forward/turn inputs into per-side motor speeds.
+//! Speeds are signed percentages, -100 (full reverse) to 100 (full forward).
+
+/// Left and right side speeds for a forward/turn input
+///
+/// x: -100 to 100 (left to right)
+/// y: -100 to 100 (backward to forward)
+///
+/// ```text
+/// left_speed  = forward_speed + turn_speed
+/// right_speed = forward_speed - turn_speed
+/// ```
+///
+/// Both results are clamped to -100..=100.
+pub fn tank_mix(x: i8, y: i8) -> (i8, i8) {
+    let left_speed = y.saturating_add(x).clamp(-100, 100);
+    let right_speed = y.saturating_sub(x).clamp(-100, 100);
+    (left_speed, right_speed)
+}
+
+/// Left and right side speeds to spin in place
+///
+/// speed: -100 to 100 (CCW to CW)
+pub fn spin_mix(speed: i8) -> (i8, i8) {
+    let speed = speed.clamp(-100, 100);
+    (speed, -speed)
+}
+
+/// Side speeds for driving upside down
+///
+/// Flipped over, each side's wheels end up on the other side of the bot and
+/// turn the opposite way relative to the driver, so the sides swap and
+/// forward and reverse swap. Spins come out unchanged.
+pub fn invert_mix(left: i8, right: i8) -> (i8, i8) {
+    (right.saturating_neg(), left.saturating_neg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documented_mixing_examples() {
+        assert_eq!(tank_mix(0, 50), (50, 50));
+        assert_eq!(tank_mix(0, -50), (-50, -50));
+        assert_eq!(tank_mix(30, 50), (80, 20));
+        assert_eq!(tank_mix(-20, -40), (-60, -20));
+        assert_eq!(tank_mix(50, 0), (50, -50));
+    }
+
+    #[test]
+    fn mixing_clamps_to_full_scale() {
+        assert_eq!(tank_mix(100, 100), (100, 0));
+        assert_eq!(tank_mix(-100, -100), (-100, 0));
+        assert_eq!(tank_mix(i8::MIN, i8::MAX), (-1, 100));
+    }
+
+    #[test]
+    fn spin_drives_sides_in_opposition() {
+        assert_eq!(spin_mix(40), (40, -40));
+        assert_eq!(spin_mix(-100), (-100, 100));
+        assert_eq!(spin_mix(i8::MIN), (-100, 100));
+    }
+
+    #[test]
+    fn inverted_swaps_sides_and_direction() {
+        assert_eq!(invert_mix(80, 20), (-20, -80));
+        assert_eq!(invert_mix(40, -40), (40, -40));
+        assert_eq!(invert_mix(i8::MIN, 0), (0, i8::MAX));
+    }
+}
@@ -0,0 +1,508 @@
+//! Motor driver abstraction
+//!
+//! [`MotorDriver`] is the one interface the drive code talks to. Speeds are
+//! signed percentages, -100 (full reverse) to 100 (full forward), the same
+//! scale the mixing functions produce. Implementations are generic over the
+//! `embedded-hal` 1.0 pin and PWM traits so they work with any HAL and can be
+//! exercised with mock pins on a host.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::pwm::SetDutyCycle;
+
+/// Failure talking to the motor driver hardware
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MotorError {
+    /// A direction or enable pin could not be set
+    Pin,
+    /// A PWM duty cycle could not be set
+    Pwm,
+}
+
+pub trait MotorDriver {
+    /// Drive at a signed speed, -100 to 100; 0 coasts
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError>;
+
+    /// Let the motor spin down freely
+    fn coast(&mut self) -> Result<(), MotorError>;
+
+    /// Short the motor to stop it as quickly as possible
+    fn brake(&mut self) -> Result<(), MotorError>;
+
+    /// Allow or block output; a disabled motor coasts and ignores speeds
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError>;
+}
+
+fn pin<P: OutputPin>(pin: &mut P, high: bool) -> Result<(), MotorError> {
+    let result = if high { pin.set_high() } else { pin.set_low() };
+    result.map_err(|_| MotorError::Pin)
+}
+
+fn duty_percent<P: SetDutyCycle>(pwm: &mut P, percent: u8) -> Result<(), MotorError> {
+    pwm.set_duty_cycle_percent(percent.min(100))
+        .map_err(|_| MotorError::Pwm)
+}
+
+/// TB6612FNG channel: two direction pins plus a PWM speed input
+///
+/// Also fits any driver with the same IN1/IN2/EN scheme, such as the L298N.
+/// The shared STBY pin is not owned here; see `TankDriveController`.
+pub struct Tb6612Motor<IN1, IN2, PWM> {
+    in1: IN1,
+    in2: IN2,
+    pwm: PWM,
+    enabled: bool,
+}
+
+impl<IN1: OutputPin, IN2: OutputPin, PWM: SetDutyCycle> Tb6612Motor<IN1, IN2, PWM> {
+    pub fn new(in1: IN1, in2: IN2, pwm: PWM) -> Result<Self, MotorError> {
+        let mut motor = Tb6612Motor { in1, in2, pwm, enabled: true };
+        motor.coast()?;
+        Ok(motor)
+    }
+}
+
+impl<IN1: OutputPin, IN2: OutputPin, PWM: SetDutyCycle> MotorDriver for Tb6612Motor<IN1, IN2, PWM> {
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError> {
+        if !self.enabled || speed == 0 {
+            return self.coast();
+        }
+        pin(&mut self.in1, speed > 0)?;
+        pin(&mut self.in2, speed < 0)?;
+        duty_percent(&mut self.pwm, speed.unsigned_abs())
+    }
+
+    fn coast(&mut self) -> Result<(), MotorError> {
+        pin(&mut self.in1, false)?;
+        pin(&mut self.in2, false)?;
+        duty_percent(&mut self.pwm, 0)
+    }
+
+    fn brake(&mut self) -> Result<(), MotorError> {
+        pin(&mut self.in1, true)?;
+        pin(&mut self.in2, true)?;
+        duty_percent(&mut self.pwm, 100)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError> {
+        self.enabled = enabled;
+        if enabled { Ok(()) } else { self.coast() }
+    }
+}
+
+/// DRV8833-style channel: both inputs are PWM outputs
+///
+/// Uses fast decay: the active input carries the duty cycle while the other
+/// is held low. Both high brakes, both low coasts. The nSLEEP pin is shared
+/// between channels and handled like the TB6612FNG STBY pin.
+pub struct DualPwmMotor<A, B> {
+    in1: A,
+    in2: B,
+    enabled: bool,
+}
+
+impl<A: SetDutyCycle, B: SetDutyCycle> DualPwmMotor<A, B> {
+    pub fn new(in1: A, in2: B) -> Result<Self, MotorError> {
+        let mut motor = DualPwmMotor { in1, in2, enabled: true };
+        motor.coast()?;
+        Ok(motor)
+    }
+}
+
+impl<A: SetDutyCycle, B: SetDutyCycle> MotorDriver for DualPwmMotor<A, B> {
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError> {
+        if !self.enabled || speed == 0 {
+            return self.coast();
+        }
+        let duty = speed.unsigned_abs();
+        if speed > 0 {
+            duty_percent(&mut self.in2, 0)?;
+            duty_percent(&mut self.in1, duty)
+        } else {
+            duty_percent(&mut self.in1, 0)?;
+            duty_percent(&mut self.in2, duty)
+        }
+    }
+
+    fn coast(&mut self) -> Result<(), MotorError> {
+        duty_percent(&mut self.in1, 0)?;
+        duty_percent(&mut self.in2, 0)
+    }
+
+    fn brake(&mut self) -> Result<(), MotorError> {
+        duty_percent(&mut self.in1, 100)?;
+        duty_percent(&mut self.in2, 100)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError> {
+        self.enabled = enabled;
+        if enabled { Ok(()) } else { self.coast() }
+    }
+}
+
+/// Front and back motor on the same side, always commanded together
+pub struct MotorPair<M> {
+    pub front: M,
+    pub back: M,
+}
+
+impl<M: MotorDriver> MotorPair<M> {
+    pub fn new(front: M, back: M) -> Self {
+        MotorPair { front, back }
+    }
+}
+
+impl<M: MotorDriver> MotorDriver for MotorPair<M> {
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError> {
+        self.front.set_speed(speed)?;
+        self.back.set_speed(speed)
+    }
+
+    fn coast(&mut self) -> Result<(), MotorError> {
+        self.front.coast()?;
+        self.back.coast()
+    }
+
+    fn brake(&mut self) -> Result<(), MotorError> {
+        self.front.brake()?;
+        self.back.brake()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError> {
+        self.front.set_enabled(enabled)?;
+        self.back.set_enabled(enabled)
+    }
+}
+
+/// Per-motor correction for wiring and mechanical differences
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MotorCalibration {
+    /// Flip direction for a motor wired or mounted backwards
+    pub invert: bool,
+    /// Speed scaling in percent, e.g. -5 makes a motor 5% weaker so a bot
+    /// that pulls to one side tracks straight
+    pub trim: i8,
+    /// Duty cap in percent at full command
+    pub max_duty: u8,
+    /// Smallest non-zero duty in percent, enough to overcome stiction
+    pub min_duty: u8,
+}
+
+impl MotorCalibration {
+    /// Pass commands through unchanged
+    pub const DEFAULT: Self = MotorCalibration {
+        invert: false,
+        trim: 0,
+        max_duty: 100,
+        min_duty: 0,
+    };
+
+    /// Map a commanded speed (-100 to 100) to the speed sent to the driver
+    ///
+    /// Zero stays zero; any other command is trimmed, then scaled into
+    /// `min_duty..=max_duty` so small stick movements still turn the wheel.
+    pub fn apply(&self, speed: i8) -> i8 {
+        if speed == 0 {
+            return 0;
+        }
+        let magnitude = speed.unsigned_abs().min(100) as i32;
+        let trimmed = (magnitude * (100 + self.trim as i32) / 100).clamp(0, 100);
+        if trimmed == 0 {
+            return 0;
+        }
+
+        let max = self.max_duty.min(100) as i32;
+        let min = (self.min_duty as i32).min(max);
+        let duty = min + trimmed * (max - min) / 100;
+
+        let forward = (speed > 0) != self.invert;
+        if forward { duty as i8 } else { -duty as i8 }
+    }
+}
+
+impl Default for MotorCalibration {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Applies a [`MotorCalibration`] to every speed sent to the inner driver
+pub struct CalibratedMotor<M> {
+    motor: M,
+    calibration: MotorCalibration,
+}
+
+impl<M: MotorDriver> CalibratedMotor<M> {
+    pub fn new(motor: M, calibration: MotorCalibration) -> Self {
+        CalibratedMotor { motor, calibration }
+    }
+
+    pub fn calibration(&self) -> MotorCalibration {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: MotorCalibration) {
+        self.calibration = calibration;
+    }
+}
+
+impl<M: MotorDriver> MotorDriver for CalibratedMotor<M> {
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError> {
+        self.motor.set_speed(self.calibration.apply(speed))
+    }
+
+    fn coast(&mut self) -> Result<(), MotorError> {
+        self.motor.coast()
+    }
+
+    fn brake(&mut self) -> Result<(), MotorError> {
+        self.motor.brake()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError> {
+        self.motor.set_enabled(enabled)
+    }
+}
+
+/// Standard RC servo pulse limits in microseconds
+pub const RC_PULSE_MIN_US: u32 = 1000;
+pub const RC_PULSE_NEUTRAL_US: u32 = 1500;
+pub const RC_PULSE_MAX_US: u32 = 2000;
+
+/// Duty cycle value that produces a `pulse_us` pulse in a `period_us` frame
+pub fn pulse_to_duty(pulse_us: u32, period_us: u32, max_duty: u16) -> u16 {
+    let pulse_us = pulse_us.min(period_us);
+    ((pulse_us as u64 * max_duty as u64) / period_us as u64) as u16
+}
+
+/// Bidirectional brushed ESC driven by RC servo pulses
+///
+/// 1500 µs is neutral, 1000 µs full reverse and 2000 µs full forward. ESCs
+/// brake or coast at neutral depending on their own settings, so both map to
+/// neutral here. Disabling stops the pulses entirely, which trips the ESC's
+/// own signal-loss failsafe.
+pub struct RcEscMotor<P> {
+    pwm: P,
+    period_us: u32,
+    enabled: bool,
+}
+
+impl<P: SetDutyCycle> RcEscMotor<P> {
+    /// `period_us` is the PWM frame length the slice was configured for,
+    /// 20_000 for the usual 50 Hz
+    pub fn new(pwm: P, period_us: u32) -> Result<Self, MotorError> {
+        let mut motor = RcEscMotor { pwm, period_us, enabled: true };
+        motor.coast()?;
+        Ok(motor)
+    }
+
+    fn pulse(&mut self, pulse_us: u32) -> Result<(), MotorError> {
+        let duty = pulse_to_duty(pulse_us, self.period_us, self.pwm.max_duty_cycle());
+        self.pwm.set_duty_cycle(duty).map_err(|_| MotorError::Pwm)
+    }
+}
+
+impl<P: SetDutyCycle> MotorDriver for RcEscMotor<P> {
+    fn set_speed(&mut self, speed: i8) -> Result<(), MotorError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let half_range = (RC_PULSE_MAX_US - RC_PULSE_NEUTRAL_US) as i32;
+        let offset = speed.clamp(-100, 100) as i32 * half_range / 100;
+        self.pulse((RC_PULSE_NEUTRAL_US as i32 + offset) as u32)
+    }
+
+    fn coast(&mut self) -> Result<(), MotorError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.pulse(RC_PULSE_NEUTRAL_US)
+    }
+
+    fn brake(&mut self) -> Result<(), MotorError> {
+        self.coast()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), MotorError> {
+        self.enabled = enabled;
+        if enabled {
+            // ESCs arm on a steady neutral signal
+            self.pulse(RC_PULSE_NEUTRAL_US)
+        } else {
+            self.pwm.set_duty_cycle(0).map_err(|_| MotorError::Pwm)
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    //! Recording stand-ins for `embedded-hal` pins and PWM outputs
+
+    use core::convert::Infallible;
+
+    use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+    use embedded_hal::pwm::{ErrorType as PwmErrorType, SetDutyCycle};
+
+    #[derive(Default)]
+    pub struct MockPin {
+        pub high: bool,
+    }
+
+    impl PinErrorType for MockPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.high = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.high = true;
+            Ok(())
+        }
+    }
+
+    pub struct MockPwm {
+        pub max: u16,
+        pub duty: u16,
+    }
+
+    impl Default for MockPwm {
+        fn default() -> Self {
+            MockPwm { max: 100, duty: 0 }
+        }
+    }
+
+    impl PwmErrorType for MockPwm {
+        type Error = Infallible;
+    }
+
+    impl SetDutyCycle for MockPwm {
+        fn max_duty_cycle(&self) -> u16 {
+            self.max
+        }
+
+        fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+            self.duty = duty;
+            Ok(())
+        }
+    }
+
+    impl super::Tb6612Motor<MockPin, MockPin, MockPwm> {
+        pub fn in1_high(&self) -> bool {
+            self.in1.high
+        }
+
+        pub fn in2_high(&self) -> bool {
+            self.in2.high
+        }
+
+        pub fn pwm_duty(&self) -> u16 {
+            self.pwm.duty
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{MockPin, MockPwm};
+    use super::*;
+
+    #[test]
+    fn tb6612_direction_and_duty() {
+        let mut motor = Tb6612Motor::new(MockPin::default(), MockPin::default(), MockPwm::default()).unwrap();
+
+        motor.set_speed(60).unwrap();
+        assert!(motor.in1.high && !motor.in2.high);
+        assert_eq!(motor.pwm.duty, 60);
+
+        motor.set_speed(-100).unwrap();
+        assert!(!motor.in1.high && motor.in2.high);
+        assert_eq!(motor.pwm.duty, 100);
+
+        motor.brake().unwrap();
+        assert!(motor.in1.high && motor.in2.high);
+
+        motor.set_enabled(false).unwrap();
+        motor.set_speed(80).unwrap();
+        assert!(!motor.in1.high && !motor.in2.high);
+        assert_eq!(motor.pwm.duty, 0);
+    }
+
+    #[test]
+    fn dual_pwm_drives_one_input_at_a_time() {
+        let mut motor = DualPwmMotor::new(MockPwm::default(), MockPwm::default()).unwrap();
+
+        motor.set_speed(-40).unwrap();
+        assert_eq!((motor.in1.duty, motor.in2.duty), (0, 40));
+
+        motor.set_speed(25).unwrap();
+        assert_eq!((motor.in1.duty, motor.in2.duty), (25, 0));
+
+        motor.brake().unwrap();
+        assert_eq!((motor.in1.duty, motor.in2.duty), (100, 100));
+    }
+
+    #[test]
+    fn calibration_inverts_trims_and_scales() {
+        assert_eq!(MotorCalibration::DEFAULT.apply(-37), -37);
+
+        let inverted = MotorCalibration { invert: true, ..MotorCalibration::DEFAULT };
+        assert_eq!(inverted.apply(50), -50);
+
+        let weaker = MotorCalibration { trim: -10, ..MotorCalibration::DEFAULT };
+        assert_eq!(weaker.apply(100), 90);
+        assert_eq!(weaker.apply(-50), -45);
+
+        let scaled = MotorCalibration { min_duty: 20, max_duty: 80, ..MotorCalibration::DEFAULT };
+        assert_eq!(scaled.apply(0), 0);
+        assert_eq!(scaled.apply(1), 20);
+        assert_eq!(scaled.apply(50), 50);
+        assert_eq!(scaled.apply(-100), -80);
+    }
+
+    #[test]
+    fn calibrated_motor_applies_on_every_speed() {
+        let calibration = MotorCalibration { invert: true, max_duty: 50, ..MotorCalibration::DEFAULT };
+        let inner = DualPwmMotor::new(MockPwm::default(), MockPwm::default()).unwrap();
+        let mut motor = CalibratedMotor::new(inner, calibration);
+
+        motor.set_speed(100).unwrap();
+        assert_eq!((motor.motor.in1.duty, motor.motor.in2.duty), (0, 50));
+    }
+
+    #[test]
+    fn pair_commands_both_motors() {
+        let motor = || DualPwmMotor::new(MockPwm::default(), MockPwm::default()).unwrap();
+        let mut side = MotorPair::new(motor(), motor());
+
+        side.set_speed(-30).unwrap();
+        assert_eq!(side.front.in2.duty, 30);
+        assert_eq!(side.back.in2.duty, 30);
+
+        side.brake().unwrap();
+        assert_eq!(side.front.in1.duty, 100);
+        assert_eq!(side.back.in1.duty, 100);
+    }
+
+    #[test]
+    fn rc_esc_maps_speed_to_pulse_width() {
+        // 20 ms frame with 20_000 counts: one count per microsecond
+        let pwm = MockPwm { max: 20_000, duty: 0 };
+        let mut motor = RcEscMotor::new(pwm, 20_000).unwrap();
+        assert_eq!(motor.pwm.duty, 1500);
+
+        motor.set_speed(100).unwrap();
+        assert_eq!(motor.pwm.duty, 2000);
+        motor.set_speed(-50).unwrap();
+        assert_eq!(motor.pwm.duty, 1250);
+
+        motor.set_enabled(false).unwrap();
+        assert_eq!(motor.pwm.duty, 0);
+        motor.set_speed(100).unwrap();
+        assert_eq!(motor.pwm.duty, 0);
+    }
+}
@@ -0,0 +1,119 @@
+//! Panic location that survives a reset
+//!
+//! The panic handler writes one of these into RAM that the runtime does not
+//! zero, then resets. On the next boot the record is checked and logged, so
+//! a panic in the arena is still visible once a probe is attached. RAM that
+//! was never written holds garbage, so the record carries a magic number and
+//! a checksum and is ignored unless both match.
+
+/// Bytes of the source path kept; the end of the path is the useful part
+pub const FILE_LEN: usize = 40;
+
+const MAGIC: u32 = 0xDEAD_C0DE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct PanicRecord {
+    magic: u32,
+    pub core: u8,
+    file_len: u8,
+    pub line: u32,
+    pub column: u32,
+    file: [u8; FILE_LEN],
+    checksum: u32,
+}
+
+impl PanicRecord {
+    pub fn new(core: u8, file: &str, line: u32, column: u32) -> Self {
+        // Keep the tail of long paths, starting on a character boundary
+        let mut start = file.len().saturating_sub(FILE_LEN);
+        while !file.is_char_boundary(start) {
+            start += 1;
+        }
+        let tail = &file.as_bytes()[start..];
+
+        let mut record = PanicRecord {
+            magic: MAGIC,
+            core,
+            file_len: tail.len() as u8,
+            line,
+            column,
+            file: [0; FILE_LEN],
+            checksum: 0,
+        };
+        record.file[..tail.len()].copy_from_slice(tail);
+        record.checksum = record.compute_checksum();
+        record
+    }
+
+    /// Record that reads as invalid, for clearing the slot after a boot
+    pub const fn empty() -> Self {
+        PanicRecord {
+            magic: 0,
+            core: 0,
+            file_len: 0,
+            line: 0,
+            column: 0,
+            file: [0; FILE_LEN],
+            checksum: 0,
+        }
+    }
+
+    /// True if this was written by [`PanicRecord::new`] and not garbage
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && self.file_len as usize <= FILE_LEN
+            && self.checksum == self.compute_checksum()
+    }
+
+    /// Tail of the source path, or "?" if it does not decode
+    pub fn file(&self) -> &str {
+        let len = (self.file_len as usize).min(FILE_LEN);
+        core::str::from_utf8(&self.file[..len]).unwrap_or("?")
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        let header = [self.core, self.file_len];
+        let words = [self.line.to_le_bytes(), self.column.to_le_bytes()];
+        header
+            .iter()
+            .chain(words.iter().flatten())
+            .chain(self.file.iter())
+            .fold(MAGIC, |sum, &byte| sum.rotate_left(5) ^ byte as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_record_is_valid() {
+        let record = PanicRecord::new(1, "src/control.rs", 42, 7);
+        assert!(record.is_valid());
+        assert_eq!(record.file(), "src/control.rs");
+        assert_eq!((record.core, record.line, record.column), (1, 42, 7));
+        assert!(!PanicRecord::empty().is_valid());
+    }
+
+    #[test]
+    fn long_paths_keep_the_tail() {
+        let path = "/home/builder/.cargo/registry/src/embassy-rp-0.4.0/src/pwm.rs";
+        let record = PanicRecord::new(0, path, 1, 1);
+        assert_eq!(record.file().len(), FILE_LEN);
+        assert!(path.ends_with(record.file()));
+
+        // A multi-byte character straddling the cut is dropped whole
+        let path = "é".repeat(FILE_LEN) + "x";
+        let record = PanicRecord::new(0, &path, 1, 1);
+        assert_eq!(record.file().len(), FILE_LEN - 1);
+        assert!(path.ends_with(record.file()));
+    }
+
+    #[test]
+    fn corruption_invalidates() {
+        let mut record = PanicRecord::new(0, "src/main.rs", 10, 5);
+        record.line = 11;
+        assert!(!record.is_valid());
+    }
+}
@@ -0,0 +1,233 @@
+//! Servo-style pulse inputs: one PWM pulse per channel, or a PPM stream
+//!
+//! Receivers without a serial output send each channel as a 1000 to 2000 µs
+//! pulse, either on its own wire or back to back on one PPM wire, where
+//! each channel is the time between rising edges and a gap of several
+//! milliseconds marks the end of the frame. The firmware measures the
+//! widths; this turns them into the same [`RcFrame`] SBUS and CRSF produce,
+//! so [`RcMapper`](crate::rc::RcMapper) and the channel map apply unchanged.
+//!
+//! Unlike the serial protocols, nothing here says the link is gone, and
+//! receivers often signal failsafe by simply stopping the pulses. So every
+//! channel is watched on its own, and one going quiet is a failsafe.
+
+use crate::config::{PPM_SYNC_GAP_US, PULSE_LOSS_MS};
+use crate::input::LinkQuality;
+use crate::rc::{ChannelMapping, RcFrame, CHANNEL_CENTRE, CHANNEL_COUNT, CHANNEL_MAX, CHANNEL_MIN};
+
+/// Nominal pulse range, full travel one way to the other
+pub const PULSE_MIN_US: u32 = 1000;
+pub const PULSE_MAX_US: u32 = 2000;
+
+/// Pulses this far outside the nominal range are glitches, not trims
+const PULSE_MARGIN_US: u32 = 200;
+
+/// True if `width_us` is plausibly a channel pulse
+pub fn pulse_valid(width_us: u32) -> bool {
+    (PULSE_MIN_US - PULSE_MARGIN_US..=PULSE_MAX_US + PULSE_MARGIN_US).contains(&width_us)
+}
+
+/// Channel value, in the SBUS and CRSF units, for a pulse width
+pub fn pulse_to_channel(width_us: u32) -> u16 {
+    let offset = width_us.clamp(PULSE_MIN_US, PULSE_MAX_US) - PULSE_MIN_US;
+    let span = (CHANNEL_MAX - CHANNEL_MIN) as u32;
+    CHANNEL_MIN + (offset * span / (PULSE_MAX_US - PULSE_MIN_US)) as u16
+}
+
+/// Bit mask of radio channels, counting from 1 as radios do, for
+/// [`PulseChannels::new`]
+pub fn channel_mask(channels: impl IntoIterator<Item = u8>) -> u16 {
+    channels
+        .into_iter()
+        .filter(|&channel| (1..=CHANNEL_COUNT as u8).contains(&channel))
+        .fold(0, |mask, channel| mask | 1 << (channel - 1))
+}
+
+/// Mapped channels a PPM receiver sending `count` channels carries, for
+/// [`PulseChannels::new`]
+pub fn ppm_channel_mask(map: &[ChannelMapping], count: u8) -> u16 {
+    channel_mask(map.iter().map(|mapping| mapping.channel).filter(|&channel| channel <= count))
+}
+
+/// What one interval in a PPM stream was
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PpmEvent {
+    /// Pulse width for a channel, counting from 0
+    Channel(u8, u32),
+    /// The sync gap after a frame with this many channels
+    FrameEnd(u8),
+}
+
+/// Splits a PPM stream, given as the times between rising edges, into
+/// channels
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PpmDecoder {
+    /// Next channel, None until the first sync gap
+    next: Option<u8>,
+}
+
+impl PpmDecoder {
+    pub const fn new() -> Self {
+        PpmDecoder { next: None }
+    }
+
+    pub fn push(&mut self, interval_us: u32) -> Option<PpmEvent> {
+        if interval_us >= PPM_SYNC_GAP_US {
+            let count = self.next.replace(0)?;
+            return Some(PpmEvent::FrameEnd(count));
+        }
+        let channel = self.next?;
+        if channel as usize >= CHANNEL_COUNT {
+            // Missed the sync gap; wait for the next one
+            self.next = None;
+            return None;
+        }
+        self.next = Some(channel + 1);
+        // A glitch still takes its slot, so later channels stay in place
+        pulse_valid(interval_us).then_some(PpmEvent::Channel(channel, interval_us))
+    }
+}
+
+/// Latest pulse on each channel, and which have gone quiet
+pub struct PulseChannels {
+    /// Channels a pulse is expected on, bit 0 for channel 1
+    expected: u16,
+    channels: [u16; CHANNEL_COUNT],
+    /// Uptime of the last good pulse per channel
+    seen_ms: [Option<u64>; CHANNEL_COUNT],
+}
+
+impl PulseChannels {
+    /// Watch the channels in `expected`, bit 0 for channel 1. Any others
+    /// read centre, which leaves sticks still and switches off.
+    pub const fn new(expected: u16) -> Self {
+        PulseChannels {
+            expected,
+            channels: [CHANNEL_CENTRE; CHANNEL_COUNT],
+            seen_ms: [None; CHANNEL_COUNT],
+        }
+    }
+
+    /// Record a pulse on `channel`, counting from 0; glitches are ignored
+    pub fn update(&mut self, channel: u8, width_us: u32, now_ms: u64) {
+        let index = channel as usize;
+        if index < CHANNEL_COUNT && pulse_valid(width_us) {
+            self.channels[index] = pulse_to_channel(width_us);
+            self.seen_ms[index] = Some(now_ms);
+        }
+    }
+
+    /// Expected channels with no good pulse in the last `PULSE_LOSS_MS`,
+    /// bit 0 for channel 1
+    pub fn lost(&self, now_ms: u64) -> u16 {
+        (0..CHANNEL_COUNT)
+            .filter(|&i| self.expected & (1 << i) != 0)
+            .filter(|&i| !self.seen_ms[i].is_some_and(|seen| now_ms.saturating_sub(seen) <= PULSE_LOSS_MS))
+            .fold(0, |mask, i| mask | 1 << i)
+    }
+
+    /// The current channels; any lost channel makes it a failsafe frame
+    pub fn frame(&self, now_ms: u64) -> RcFrame {
+        let lost = self.lost(now_ms);
+        let expected = self.expected.count_ones().max(1);
+        let alive = expected - lost.count_ones().min(expected);
+        RcFrame {
+            channels: self.channels,
+            link: LinkQuality {
+                percent: (alive * 100 / expected) as u8,
+                rssi_dbm: None,
+                failsafe: lost != 0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RC_CHANNEL_MAP;
+
+    #[test]
+    fn scales_pulses() {
+        assert_eq!(pulse_to_channel(1000), CHANNEL_MIN);
+        assert_eq!(pulse_to_channel(2000), CHANNEL_MAX);
+        assert_eq!(pulse_to_channel(900), CHANNEL_MIN);
+        assert!(pulse_to_channel(1500).abs_diff(CHANNEL_CENTRE) <= 1);
+        assert!(pulse_valid(950) && pulse_valid(2100));
+        assert!(!pulse_valid(300) && !pulse_valid(4000));
+        assert_eq!(channel_mask([1, 2, 5, 0, 17]), 0b1_0011);
+    }
+
+    #[test]
+    fn splits_a_ppm_stream() {
+        // Edge-to-edge times from an 8-channel receiver, joined mid-frame,
+        // with one glitched channel in the second frame
+        let stream = [
+            1500, 1500, 12100, 1500, 2000, 1000, 1500, 1900, 1100, 1500, 1500, 8400, 1500, 2000, 350, 1500,
+            1900, 1100, 1500, 1500, 8400,
+        ];
+
+        let mut decoder = PpmDecoder::new();
+        let events: Vec<_> = stream.iter().filter_map(|&us| decoder.push(us)).collect();
+        assert_eq!(events.len(), 8 + 1 + 7 + 1);
+        assert_eq!(events[0], PpmEvent::Channel(0, 1500));
+        assert_eq!(events[1], PpmEvent::Channel(1, 2000));
+        assert_eq!(events[8], PpmEvent::FrameEnd(8));
+        // Channel 3 is skipped, the rest keep their numbers
+        assert_eq!(events[11], PpmEvent::Channel(3, 1500));
+        assert_eq!(events[16], PpmEvent::FrameEnd(8));
+    }
+
+    #[test]
+    fn runaway_ppm_waits_for_sync() {
+        let mut decoder = PpmDecoder::new();
+        decoder.push(10_000);
+        let events: Vec<_> = (0..20).filter_map(|_| decoder.push(1500)).collect();
+        assert_eq!(events.len(), CHANNEL_COUNT);
+        assert_eq!(decoder.push(10_000), None);
+        assert_eq!(decoder.push(1500), Some(PpmEvent::Channel(0, 1500)));
+    }
+
+    #[test]
+    fn each_channel_can_go_quiet() {
+        // Channels 1, 2 and 5 wired
+        let mut pulses = PulseChannels::new(0b1_0011);
+        assert_eq!(pulses.lost(0), 0b1_0011);
+        assert!(pulses.frame(0).link.failsafe);
+
+        for channel in [0, 1, 4] {
+            pulses.update(channel, 2000, 10);
+        }
+        let frame = pulses.frame(20);
+        assert!(!frame.link.failsafe);
+        assert_eq!(frame.link.percent, 100);
+        assert_eq!(frame.channels[0], CHANNEL_MAX);
+        assert_eq!(frame.channels[2], CHANNEL_CENTRE);
+
+        // Channel 5 stops, the others keep going
+        let later = 10 + PULSE_LOSS_MS + 5;
+        pulses.update(0, 1000, later);
+        pulses.update(1, 1000, later);
+        pulses.update(4, 5000, later);
+        assert_eq!(pulses.lost(later), 0b1_0000);
+        let frame = pulses.frame(later);
+        assert!(frame.link.failsafe);
+        assert_eq!(frame.link.percent, 66);
+    }
+
+    #[test]
+    fn short_ppm_receiver_is_not_failsafe() {
+        // A 6-channel receiver leaves the kill switch on channel 7 unwired
+        let expected = ppm_channel_mask(&RC_CHANNEL_MAP, 6);
+        assert_eq!(expected, 0b11_1111);
+
+        let mut pulses = PulseChannels::new(expected);
+        for channel in 0..6 {
+            pulses.update(channel, 1500, 10);
+        }
+        let frame = pulses.frame(20);
+        assert!(!frame.link.failsafe);
+        assert_eq!(frame.channels[6], CHANNEL_CENTRE);
+    }
+}
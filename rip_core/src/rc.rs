@@ -0,0 +1,360 @@
+//! RC radio receivers: channel values, the channel map and the translation
+//! into normalized controller frames
+//!
+//! SBUS and CRSF receivers both deliver 16 channels of 11 bits in the same
+//! 172 to 1811 range, centred on 992; [`sbus`](crate::sbus) and
+//! [`crsf`](crate::crsf) parse the wire formats into an [`RcFrame`], and
+//! [`RcMapper`] turns that into a [`ControllerData`] the state machine
+//! already understands.
+//!
+//! A radio has switches where the PS2 pad has button gestures, so the mapper
+//! turns switch flips into the same gestures: flipping the arm switch on
+//! holds both triggers and presses Start, flipping it off presses Select,
+//! and so on. Each gesture is held for [`RC_SWITCH_PULSE_MS`] so core 1
+//! cannot miss it between samples. Only flips count: a switch already on
+//! when the receiver first reports, or when the link comes back, does
+//! nothing until it is cycled.
+
+use crate::config::{RC_SWITCH_PULSE_MS, WEAPON_ARM_BUTTON, WEAPON_DISARM_BUTTON};
+use crate::input::{Buttons, ControllerData, LinkQuality};
+
+pub const CHANNEL_COUNT: usize = 16;
+
+/// Channel values at full deflection and centre, for both protocols
+pub const CHANNEL_MIN: u16 = 172;
+pub const CHANNEL_CENTRE: u16 = 992;
+pub const CHANNEL_MAX: u16 = 1811;
+
+/// A two- or three-position switch reads on above this, about 75% travel
+pub const SWITCH_ON_THRESHOLD: u16 = (CHANNEL_CENTRE + CHANNEL_MAX) / 2;
+
+/// Packed size of 16 channels of 11 bits
+pub(crate) const PACKED_CHANNELS_SIZE: usize = 22;
+
+/// One decoded set of channels
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RcFrame {
+    /// Raw channel values, channel 1 first
+    pub channels: [u16; CHANNEL_COUNT],
+    pub link: LinkQuality,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RcError {
+    /// Start or end marker missing; the decoder has lost frame sync
+    BadFraming,
+    BadCrc,
+    BadLength,
+}
+
+/// A byte-stream decoder for one receiver protocol
+pub trait RcDecoder {
+    fn push(&mut self, byte: u8);
+
+    /// The next complete set of channels or error, if any
+    fn next_frame(&mut self) -> Option<Result<RcFrame, RcError>>;
+}
+
+/// Unpack 16 channels of 11 bits, least significant bit first
+pub(crate) fn unpack_channels(bytes: &[u8; PACKED_CHANNELS_SIZE]) -> [u16; CHANNEL_COUNT] {
+    let mut channels = [0; CHANNEL_COUNT];
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    let mut bytes = bytes.iter();
+    for channel in channels.iter_mut() {
+        while count < 11 {
+            bits |= (*bytes.next().unwrap_or(&0) as u32) << count;
+            count += 8;
+        }
+        *channel = (bits & 0x7FF) as u16;
+        bits >>= 11;
+        count -= 11;
+    }
+    channels
+}
+
+/// Signed axis, -127 to 127, from a centred channel
+pub fn channel_axis(raw: u16) -> i8 {
+    let half_range = (CHANNEL_MAX - CHANNEL_CENTRE) as i32;
+    let value = (raw as i32 - CHANNEL_CENTRE as i32) * 127 / half_range;
+    value.clamp(-127, 127) as i8
+}
+
+/// Trigger-style value, 0 at the bottom of the travel to 255 at the top
+pub fn channel_trigger(raw: u16) -> u8 {
+    let value = (raw.saturating_sub(CHANNEL_MIN) as u32) * 255 / (CHANNEL_MAX - CHANNEL_MIN) as u32;
+    value.min(255) as u8
+}
+
+pub fn channel_switch(raw: u16) -> bool {
+    raw >= SWITCH_ON_THRESHOLD
+}
+
+/// What a channel drives
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RcFunction {
+    /// Drive turn, as the left stick X
+    Steer,
+    /// Drive forward and back, as the left stick Y
+    Throttle,
+    /// Spin in place, as the right stick X
+    Spin,
+    /// Servo angle, as the right stick Y
+    Servo,
+    /// Weapon throttle from the bottom of the travel, as the right trigger
+    Weapon,
+    /// On arms the drive, off returns to idle, or re-arms after a dropout
+    ArmSwitch,
+    /// On arms the weapon, off makes it safe
+    WeaponSwitch,
+    /// On is the kill combo; off clears the emergency
+    KillSwitch,
+}
+
+/// One entry in the channel map
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelMapping {
+    /// Channel number, counting from 1 as radios do
+    pub channel: u8,
+    pub function: RcFunction,
+    /// Flip the direction, for sticks and switches mounted the other way
+    pub reversed: bool,
+}
+
+impl ChannelMapping {
+    pub const fn new(channel: u8, function: RcFunction) -> Self {
+        ChannelMapping { channel, function, reversed: false }
+    }
+
+    pub const fn reversed(self) -> Self {
+        ChannelMapping { reversed: true, ..self }
+    }
+
+    fn value(&self, channels: &[u16; CHANNEL_COUNT]) -> Option<u16> {
+        let raw = *channels.get((self.channel as usize).checked_sub(1)?)?;
+        Some(if self.reversed {
+            (CHANNEL_MIN + CHANNEL_MAX).saturating_sub(raw)
+        } else {
+            raw
+        })
+    }
+}
+
+/// Switch positions from the last good frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Switches {
+    arm: bool,
+    weapon: bool,
+    kill: bool,
+}
+
+/// Turns RC frames into controller frames through a channel map
+pub struct RcMapper {
+    map: &'static [ChannelMapping],
+    /// None until the first good frame, so the power-up positions are not
+    /// taken as flips
+    switches: Option<Switches>,
+    /// Gesture buttons, and whether it holds the triggers, until a time
+    pulse: Option<(u16, bool, u64)>,
+}
+
+impl RcMapper {
+    pub const fn new(map: &'static [ChannelMapping]) -> Self {
+        RcMapper { map, switches: None, pulse: None }
+    }
+
+    /// Controller frame for `frame`, received at `now_ms`
+    pub fn map(&mut self, frame: &RcFrame, now_ms: u64) -> ControllerData {
+        let mut data = ControllerData::neutral(now_ms);
+        data.link = frame.link;
+
+        let mut switches = Switches::default();
+        for mapping in self.map {
+            let Some(raw) = mapping.value(&frame.channels) else {
+                continue;
+            };
+            match mapping.function {
+                RcFunction::Steer => data.left_x = channel_axis(raw),
+                RcFunction::Throttle => data.left_y = channel_axis(raw),
+                RcFunction::Spin => data.right_x = channel_axis(raw),
+                RcFunction::Servo => data.right_y = channel_axis(raw),
+                RcFunction::Weapon => data.right_trigger = channel_trigger(raw),
+                RcFunction::ArmSwitch => switches.arm = channel_switch(raw),
+                RcFunction::WeaponSwitch => switches.weapon = channel_switch(raw),
+                RcFunction::KillSwitch => switches.kill = channel_switch(raw),
+            }
+        }
+
+        // Failsafe channels are whatever the receiver was set up to repeat,
+        // not the driver's switches
+        if frame.link.failsafe {
+            self.switches = None;
+            self.pulse = None;
+            return data;
+        }
+
+        if let Some(last) = self.switches {
+            if let Some((buttons, triggers)) = gesture(last, switches) {
+                self.pulse = Some((buttons, triggers, now_ms + RC_SWITCH_PULSE_MS));
+            }
+        }
+        self.switches = Some(switches);
+
+        if switches.kill {
+            data.buttons = Buttons::from_bits(Buttons::L1 | Buttons::R1 | Buttons::L2 | Buttons::R2);
+        }
+        match self.pulse {
+            Some((buttons, triggers, until_ms)) if now_ms < until_ms => {
+                data.buttons = Buttons::from_bits(data.buttons.bits() | buttons);
+                if triggers {
+                    data.left_trigger = 255;
+                    data.right_trigger = 255;
+                }
+            }
+            _ => self.pulse = None,
+        }
+        data
+    }
+}
+
+/// The pad gesture a switch flip stands for: buttons, and whether both
+/// triggers are held with them. The kill switch wins if several flip in the
+/// same frame.
+fn gesture(last: Switches, now: Switches) -> Option<(u16, bool)> {
+    if now.kill != last.kill {
+        return (!now.kill).then_some((Buttons::START | Buttons::SELECT, false));
+    }
+    if now.arm != last.arm {
+        return Some(if now.arm {
+            (Buttons::START, true)
+        } else {
+            (Buttons::SELECT, false)
+        });
+    }
+    if now.weapon != last.weapon {
+        return Some(if now.weapon {
+            (Buttons::L1 | WEAPON_ARM_BUTTON, false)
+        } else {
+            (WEAPON_DISARM_BUTTON, false)
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ARM_COUNTDOWN_MS, RC_CHANNEL_MAP, WEAPON_ARM_COUNTDOWN_MS};
+    use crate::state::{BotState, StateMachine, WeaponState};
+
+    /// Long enough for either arming countdown
+    const COUNTDOWN_MS: u64 = if ARM_COUNTDOWN_MS > WEAPON_ARM_COUNTDOWN_MS {
+        ARM_COUNTDOWN_MS
+    } else {
+        WEAPON_ARM_COUNTDOWN_MS
+    } + 100;
+
+    fn frame(overrides: &[(u8, u16)]) -> RcFrame {
+        let mut channels = [CHANNEL_CENTRE; CHANNEL_COUNT];
+        // Weapon throttle and switches rest at the bottom
+        for channel in [3, 5, 6, 7] {
+            channels[channel - 1] = CHANNEL_MIN;
+        }
+        for &(channel, value) in overrides {
+            channels[channel as usize - 1] = value;
+        }
+        RcFrame { channels, link: LinkQuality::WIRED }
+    }
+
+    #[test]
+    fn scales_channels() {
+        assert_eq!(channel_axis(CHANNEL_CENTRE), 0);
+        assert_eq!(channel_axis(CHANNEL_MAX), 127);
+        assert_eq!(channel_axis(CHANNEL_MIN), -127);
+        assert_eq!(channel_axis(2047), 127);
+        assert_eq!(channel_trigger(CHANNEL_MIN), 0);
+        assert_eq!(channel_trigger(CHANNEL_MAX), 255);
+        assert_eq!(channel_trigger(0), 0);
+        assert!(!channel_switch(CHANNEL_CENTRE));
+        assert!(channel_switch(CHANNEL_MAX));
+
+        let reversed = ChannelMapping::new(2, RcFunction::Throttle).reversed();
+        assert_eq!(reversed.value(&frame(&[(2, CHANNEL_MAX)]).channels), Some(CHANNEL_MIN));
+        assert_eq!(ChannelMapping::new(0, RcFunction::Steer).value(&frame(&[]).channels), None);
+    }
+
+    #[test]
+    fn switch_flips_drive_the_state_machine() {
+        let mut mapper = RcMapper::new(&RC_CHANNEL_MAP);
+        let mut sm = StateMachine::new();
+        let mut now = 0;
+        // Hold the sticks and switches for `ms`, at 100 Hz
+        let mut hold = |mapper: &mut RcMapper, sm: &mut StateMachine, frame: RcFrame, ms: u64| {
+            for _ in 0..ms / 10 {
+                now += 10;
+                sm.update(Some(&mapper.map(&frame, now)), now);
+            }
+        };
+        let off = frame(&[]);
+        let armed = frame(&[(5, CHANNEL_MAX)]);
+
+        // Arm switch already on at power-up is not a flip
+        hold(&mut mapper, &mut sm, armed, 500);
+        assert_eq!(sm.state(), BotState::Idle);
+
+        hold(&mut mapper, &mut sm, off, 200);
+        hold(&mut mapper, &mut sm, armed, 200);
+        assert_eq!(sm.state(), BotState::Arming);
+        hold(&mut mapper, &mut sm, armed, COUNTDOWN_MS);
+        assert_eq!(sm.state(), BotState::Combat);
+
+        let weapon = frame(&[(5, CHANNEL_MAX), (6, CHANNEL_MAX)]);
+        hold(&mut mapper, &mut sm, weapon, COUNTDOWN_MS);
+        assert_eq!(sm.weapon_state(), WeaponState::Armed);
+        hold(&mut mapper, &mut sm, armed, 200);
+        assert_eq!(sm.weapon_state(), WeaponState::Safe);
+
+        // Kill switch latches the emergency until it is switched off again
+        let kill = frame(&[(5, CHANNEL_MAX), (7, CHANNEL_MAX)]);
+        hold(&mut mapper, &mut sm, kill, 200);
+        assert!(matches!(sm.state(), BotState::Emergency(_)));
+        hold(&mut mapper, &mut sm, armed, 200);
+        assert_eq!(sm.state(), BotState::Idle);
+
+        hold(&mut mapper, &mut sm, off, 200);
+        hold(&mut mapper, &mut sm, armed, 200 + COUNTDOWN_MS);
+        assert_eq!(sm.state(), BotState::Combat);
+        hold(&mut mapper, &mut sm, off, 200);
+        assert_eq!(sm.state(), BotState::Idle);
+    }
+
+    #[test]
+    fn failsafe_frames_forget_switch_positions() {
+        let mut mapper = RcMapper::new(&RC_CHANNEL_MAP);
+        mapper.map(&frame(&[]), 0);
+
+        let mut failsafe = frame(&[(5, CHANNEL_MAX)]);
+        failsafe.link.failsafe = true;
+        let data = mapper.map(&failsafe, 10);
+        assert!(data.link.failsafe);
+        assert_eq!(data.buttons.bits(), 0);
+
+        // Back with the switch on: no flip until it is cycled
+        let data = mapper.map(&frame(&[(5, CHANNEL_MAX)]), 20);
+        assert_eq!(data.buttons.bits(), 0);
+        assert_eq!(data.right_trigger, 0);
+    }
+
+    #[test]
+    fn maps_sticks_and_weapon_throttle() {
+        let mut mapper = RcMapper::new(&RC_CHANNEL_MAP);
+        let data = mapper.map(&frame(&[(1, CHANNEL_MAX), (2, CHANNEL_MIN), (3, CHANNEL_MAX), (4, CHANNEL_MIN)]), 5);
+        assert_eq!((data.left_x, data.left_y, data.right_x), (127, -127, -127));
+        assert_eq!(data.right_trigger, 255);
+        assert_eq!(data.timestamp_ms, 5);
+    }
+}
@@ -0,0 +1,209 @@
+//! SBUS receiver frames
+//!
+//! 100 kbaud, 8E2 and inverted on the wire, one 25-byte frame every 7 to
+//! 14 ms:
+//!
+//! | bytes | field                                                |
+//! |-------|------------------------------------------------------|
+//! | 1     | header, `0x0F`                                       |
+//! | 22    | channels 1 to 16, 11 bits each, LSB first            |
+//! | 1     | flags: bit 2 frame lost, bit 3 failsafe              |
+//! | 1     | footer, `0x00` (SBUS2 receivers send `0x04`-`0x34`)  |
+//!
+//! There is no checksum, so the header and footer are all that keep the
+//! decoder in step.
+
+use crate::input::LinkQuality;
+use crate::rc::{unpack_channels, RcDecoder, RcError, RcFrame, PACKED_CHANNELS_SIZE};
+
+pub const FRAME_SIZE: usize = 25;
+
+const HEADER: u8 = 0x0F;
+const FLAGS: usize = 23;
+const FRAME_LOST: u8 = 1 << 2;
+const FAILSAFE: u8 = 1 << 3;
+
+/// Weight of each new frame in the link quality average, as a divisor
+const QUALITY_SMOOTHING: u16 = 8;
+
+/// The parts of a frame, before link quality is worked out
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SbusFrame {
+    pub channels: [u16; 16],
+    /// The receiver missed the transmitter's last packet and repeated the
+    /// previous values
+    pub frame_lost: bool,
+    /// The receiver has given up on the transmitter
+    pub failsafe: bool,
+}
+
+fn is_footer(byte: u8) -> bool {
+    byte == 0x00 || byte & 0x0F == 0x04
+}
+
+/// Parse one complete frame
+pub fn parse(frame: &[u8; FRAME_SIZE]) -> Result<SbusFrame, RcError> {
+    if frame[0] != HEADER || !is_footer(frame[FRAME_SIZE - 1]) {
+        return Err(RcError::BadFraming);
+    }
+    let packed: &[u8; PACKED_CHANNELS_SIZE] = frame[1..FLAGS].try_into().unwrap();
+    Ok(SbusFrame {
+        channels: unpack_channels(packed),
+        frame_lost: frame[FLAGS] & FRAME_LOST != 0,
+        failsafe: frame[FLAGS] & FAILSAFE != 0,
+    })
+}
+
+/// Reassembles frames from UART bytes and tracks link quality from the
+/// frame-lost flags
+pub struct SbusDecoder {
+    buf: [u8; FRAME_SIZE],
+    len: usize,
+    /// Share of frames with fresh data, scaled by `QUALITY_SMOOTHING`
+    quality: u16,
+}
+
+impl SbusDecoder {
+    pub const fn new() -> Self {
+        SbusDecoder {
+            buf: [0; FRAME_SIZE],
+            len: 0,
+            quality: 100 * QUALITY_SMOOTHING,
+        }
+    }
+
+    fn discard(&mut self, count: usize) {
+        self.buf.copy_within(count..self.len, 0);
+        self.len -= count;
+    }
+}
+
+impl Default for SbusDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RcDecoder for SbusDecoder {
+    fn push(&mut self, byte: u8) {
+        if self.len == FRAME_SIZE {
+            self.discard(1);
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    fn next_frame(&mut self) -> Option<Result<RcFrame, RcError>> {
+        while self.len > 0 && self.buf[0] != HEADER {
+            self.discard(1);
+        }
+        if self.len < FRAME_SIZE {
+            return None;
+        }
+
+        let result = parse(&self.buf);
+        // A bad frame may have been a channel byte that looked like a
+        // header, so only step past it
+        self.discard(if result.is_ok() { FRAME_SIZE } else { 1 });
+        Some(result.map(|frame| {
+            let fresh = if frame.frame_lost { 0 } else { 100 };
+            self.quality = self.quality - self.quality / QUALITY_SMOOTHING + fresh;
+            RcFrame {
+                channels: frame.channels,
+                link: LinkQuality {
+                    percent: (self.quality / QUALITY_SMOOTHING) as u8,
+                    rssi_dbm: None,
+                    failsafe: frame.failsafe,
+                },
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A receiver's frame with the sticks centred, channel 2 full up,
+    /// channel 3 and switches 6 and 7 at the bottom, switch 5 on
+    const GOOD: [u8; FRAME_SIZE] = [
+        0x0F, 0xE0, 0x9B, 0x38, 0x2B, 0xC0, 0x37, 0x71, 0x56, 0xB0, 0x02, 0x7C, 0xE0, 0x03, 0x1F, 0xF8, 0xC0,
+        0x07, 0x3E, 0xF0, 0x81, 0x0F, 0x7C, 0x00, 0x00,
+    ];
+    const CHANNELS: [u16; 16] = [
+        992, 1811, 172, 992, 1811, 172, 172, 992, 992, 992, 992, 992, 992, 992, 992, 992,
+    ];
+
+    /// The same receiver once the transmitter is switched off: centred
+    /// failsafe values with the frame-lost and failsafe bits set
+    const FAILSAFE_FRAME: [u8; FRAME_SIZE] = [
+        0x0F, 0xE0, 0x03, 0x1F, 0xF8, 0xC0, 0x07, 0x3E, 0xF0, 0x81, 0x0F, 0x7C, 0xE0, 0x03, 0x1F, 0xF8, 0xC0,
+        0x07, 0x3E, 0xF0, 0x81, 0x0F, 0x7C, 0x0C, 0x00,
+    ];
+
+    fn decode(decoder: &mut SbusDecoder, bytes: &[u8]) -> Vec<Result<RcFrame, RcError>> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            decoder.push(byte);
+            while let Some(frame) = decoder.next_frame() {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn parses_recorded_frames() {
+        let frame = parse(&GOOD).unwrap();
+        assert_eq!(frame.channels, CHANNELS);
+        assert!(!frame.frame_lost && !frame.failsafe);
+
+        let frame = parse(&FAILSAFE_FRAME).unwrap();
+        assert_eq!(frame.channels, [992; 16]);
+        assert!(frame.frame_lost && frame.failsafe);
+
+        let mut sbus2 = GOOD;
+        sbus2[24] = 0x14;
+        assert!(parse(&sbus2).is_ok());
+
+        let mut bad = GOOD;
+        bad[24] = 0x55;
+        assert_eq!(parse(&bad), Err(RcError::BadFraming));
+    }
+
+    #[test]
+    fn resyncs_mid_stream() {
+        // Joined partway through a frame, then two whole ones
+        let mut stream = GOOD[10..].to_vec();
+        stream.extend_from_slice(&GOOD);
+        stream.extend_from_slice(&FAILSAFE_FRAME);
+
+        let mut decoder = SbusDecoder::new();
+        let frames = decode(&mut decoder, &stream);
+        let good: Vec<_> = frames.iter().filter_map(|f| f.ok()).collect();
+        assert_eq!(good.len(), 2);
+        assert_eq!(good[0].channels, CHANNELS);
+        assert_eq!(good[0].link.percent, 100);
+        assert!(!good[0].link.failsafe);
+        assert!(good[1].link.failsafe);
+    }
+
+    #[test]
+    fn lost_frames_lower_link_quality() {
+        let mut lost = GOOD;
+        lost[FLAGS] = FRAME_LOST;
+
+        let mut decoder = SbusDecoder::new();
+        let mut stream = Vec::new();
+        for _ in 0..4 {
+            stream.extend_from_slice(&lost);
+        }
+        let frames = decode(&mut decoder, &stream);
+        let percent = frames.last().unwrap().unwrap().link.percent;
+        assert!(percent > 50 && percent < 100, "{percent}");
+
+        let frames = decode(&mut decoder, &GOOD.repeat(100));
+        assert_eq!(frames.last().unwrap().unwrap().link.percent, 100);
+    }
+}
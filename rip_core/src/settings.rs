@@ -0,0 +1,293 @@
+//! Tuning values kept in flash, so they can change without a rebuild
+//!
+//! The stored record is a header (magic, layout version, payload length),
+//! the fields in little-endian order and a CRC over everything before it.
+//! Anything that does not check out, a blank sector included, is rejected
+//! and the firmware carries on with [`Settings::DEFAULT`], which is built
+//! from the constants in `config`.
+
+use crate::config::*;
+use crate::crc::crc16;
+
+/// Layout of the payload; bump when fields are added, removed or reordered
+pub const VERSION: u16 = 1;
+
+const MAGIC: u32 = u32::from_le_bytes(*b"RIPC");
+const HEADER_SIZE: usize = 8;
+const PAYLOAD_SIZE: usize = 15;
+
+/// Bytes taken by an encoded record
+pub const RECORD_SIZE: usize = HEADER_SIZE + PAYLOAD_SIZE + 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SettingsError {
+    /// Erased flash, nothing saved yet
+    Blank,
+    BadMagic,
+    /// Saved by firmware with a different layout
+    UnsupportedVersion(u16),
+    BadLength(u16),
+    BadCrc,
+    /// Decoded fine but a value is outside what the firmware accepts
+    OutOfRange,
+}
+
+/// Driver-facing tuning values
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Settings {
+    /// PS2 controller SPI frequency in Hz
+    pub ps2_spi_frequency: u32,
+    /// State controller rate in Hz
+    pub control_loop_hz: u32,
+    /// Left stick deflection from centre treated as zero, in raw counts
+    pub stick_dead_zone: u8,
+    /// Right stick deflection needed before it spins the bot
+    pub spin_dead_zone: u8,
+    /// Trigger pressure above which the controller rumbles
+    pub rumble_threshold: u8,
+    /// Big motor strength is (pressure - subtract) * 255 / divisor
+    pub rumble_max_subtract: u8,
+    pub rumble_max_divisor: u16,
+    /// L2 and R2 must both be held past this to arm the drive
+    pub combat_mode_pressure: u8,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Settings {
+    /// Compile-time values, used until something valid is saved
+    pub const DEFAULT: Self = Settings {
+        ps2_spi_frequency: PS2_SPI_FREQUENCY,
+        control_loop_hz: CONTROL_LOOP_HZ,
+        stick_dead_zone: STICK_DEAD_ZONE,
+        spin_dead_zone: SPIN_DEAD_ZONE,
+        rumble_threshold: RUMBLE_THRESHOLD,
+        rumble_max_subtract: RUMBLE_MAX_SUBTRACT,
+        rumble_max_divisor: RUMBLE_MAX_DIVISOR,
+        combat_mode_pressure: COMBAT_MODE_PRESSURE,
+    };
+
+    /// State controller period in microseconds
+    pub fn control_loop_period_us(&self) -> u32 {
+        1_000_000 / self.control_loop_hz
+    }
+
+    /// True if every value is one the firmware can run with
+    pub fn is_valid(&self) -> bool {
+        (PS2_SPI_FREQUENCY_MIN..=500_000).contains(&self.ps2_spi_frequency)
+            && (CONTROL_LOOP_HZ_MIN..=1_000).contains(&self.control_loop_hz)
+            && self.stick_dead_zone <= 64
+            && self.spin_dead_zone <= 64
+            && self.rumble_max_divisor != 0
+            && self.combat_mode_pressure != 0
+    }
+
+    pub fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(PAYLOAD_SIZE as u16).to_le_bytes());
+
+        let payload = &mut bytes[HEADER_SIZE..HEADER_SIZE + PAYLOAD_SIZE];
+        payload[0..4].copy_from_slice(&self.ps2_spi_frequency.to_le_bytes());
+        payload[4..8].copy_from_slice(&self.control_loop_hz.to_le_bytes());
+        payload[8] = self.stick_dead_zone;
+        payload[9] = self.spin_dead_zone;
+        payload[10] = self.rumble_threshold;
+        payload[11] = self.rumble_max_subtract;
+        payload[12..14].copy_from_slice(&self.rumble_max_divisor.to_le_bytes());
+        payload[14] = self.combat_mode_pressure;
+
+        let crc = crc16(&bytes[..RECORD_SIZE - 2]);
+        bytes[RECORD_SIZE - 2..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8; RECORD_SIZE]) -> Result<Self, SettingsError> {
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+        if bytes.iter().all(|&byte| byte == 0xFF) {
+            return Err(SettingsError::Blank);
+        }
+        if u32_at(0) != MAGIC {
+            return Err(SettingsError::BadMagic);
+        }
+        match u16_at(4) {
+            VERSION => {}
+            version => return Err(SettingsError::UnsupportedVersion(version)),
+        }
+        match u16_at(6) {
+            len if len as usize == PAYLOAD_SIZE => {}
+            len => return Err(SettingsError::BadLength(len)),
+        }
+        if u16_at(RECORD_SIZE - 2) != crc16(&bytes[..RECORD_SIZE - 2]) {
+            return Err(SettingsError::BadCrc);
+        }
+
+        let p = HEADER_SIZE;
+        let settings = Settings {
+            ps2_spi_frequency: u32_at(p),
+            control_loop_hz: u32_at(p + 4),
+            stick_dead_zone: bytes[p + 8],
+            spin_dead_zone: bytes[p + 9],
+            rumble_threshold: bytes[p + 10],
+            rumble_max_subtract: bytes[p + 11],
+            rumble_max_divisor: u16_at(p + 12),
+            combat_mode_pressure: bytes[p + 14],
+        };
+        if !settings.is_valid() {
+            return Err(SettingsError::OutOfRange);
+        }
+        Ok(settings)
+    }
+}
+
+/// One field of [`Settings`], addressed by name from the console
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SettingKey {
+    Ps2SpiFrequency,
+    ControlLoopHz,
+    StickDeadZone,
+    SpinDeadZone,
+    RumbleThreshold,
+    RumbleMaxSubtract,
+    RumbleMaxDivisor,
+    CombatModePressure,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 8] = [
+        SettingKey::Ps2SpiFrequency,
+        SettingKey::ControlLoopHz,
+        SettingKey::StickDeadZone,
+        SettingKey::SpinDeadZone,
+        SettingKey::RumbleThreshold,
+        SettingKey::RumbleMaxSubtract,
+        SettingKey::RumbleMaxDivisor,
+        SettingKey::CombatModePressure,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingKey::Ps2SpiFrequency => "ps2_spi_frequency",
+            SettingKey::ControlLoopHz => "control_loop_hz",
+            SettingKey::StickDeadZone => "stick_dead_zone",
+            SettingKey::SpinDeadZone => "spin_dead_zone",
+            SettingKey::RumbleThreshold => "rumble_threshold",
+            SettingKey::RumbleMaxSubtract => "rumble_max_subtract",
+            SettingKey::RumbleMaxDivisor => "rumble_max_divisor",
+            SettingKey::CombatModePressure => "combat_mode_pressure",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.name() == name)
+    }
+
+    pub fn get(self, settings: &Settings) -> u32 {
+        match self {
+            SettingKey::Ps2SpiFrequency => settings.ps2_spi_frequency,
+            SettingKey::ControlLoopHz => settings.control_loop_hz,
+            SettingKey::StickDeadZone => settings.stick_dead_zone as u32,
+            SettingKey::SpinDeadZone => settings.spin_dead_zone as u32,
+            SettingKey::RumbleThreshold => settings.rumble_threshold as u32,
+            SettingKey::RumbleMaxSubtract => settings.rumble_max_subtract as u32,
+            SettingKey::RumbleMaxDivisor => settings.rumble_max_divisor as u32,
+            SettingKey::CombatModePressure => settings.combat_mode_pressure as u32,
+        }
+    }
+
+    /// Change one field, leaving `settings` untouched if the result would
+    /// not be valid
+    pub fn set(self, settings: &mut Settings, value: u32) -> Result<(), SettingsError> {
+        let byte = || u8::try_from(value).map_err(|_| SettingsError::OutOfRange);
+        let mut new = *settings;
+        match self {
+            SettingKey::Ps2SpiFrequency => new.ps2_spi_frequency = value,
+            SettingKey::ControlLoopHz => new.control_loop_hz = value,
+            SettingKey::StickDeadZone => new.stick_dead_zone = byte()?,
+            SettingKey::SpinDeadZone => new.spin_dead_zone = byte()?,
+            SettingKey::RumbleThreshold => new.rumble_threshold = byte()?,
+            SettingKey::RumbleMaxSubtract => new.rumble_max_subtract = byte()?,
+            SettingKey::RumbleMaxDivisor => {
+                new.rumble_max_divisor = u16::try_from(value).map_err(|_| SettingsError::OutOfRange)?
+            }
+            SettingKey::CombatModePressure => new.combat_mode_pressure = byte()?,
+        }
+        if !new.is_valid() {
+            return Err(SettingsError::OutOfRange);
+        }
+        *settings = new;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TUNED: Settings = Settings {
+        stick_dead_zone: 14,
+        control_loop_hz: 100,
+        rumble_max_divisor: 300,
+        ..Settings::DEFAULT
+    };
+
+    #[test]
+    fn defaults_are_valid_and_round_trip() {
+        assert!(Settings::DEFAULT.is_valid());
+        assert_eq!(Settings::decode(&Settings::DEFAULT.encode()), Ok(Settings::DEFAULT));
+        assert_eq!(Settings::decode(&TUNED.encode()), Ok(TUNED));
+        assert_eq!(TUNED.control_loop_period_us(), 10_000);
+    }
+
+    #[test]
+    fn rejects_blank_and_corrupt_records() {
+        assert_eq!(Settings::decode(&[0xFF; RECORD_SIZE]), Err(SettingsError::Blank));
+
+        let mut bytes = TUNED.encode();
+        bytes[HEADER_SIZE + 8] ^= 1;
+        assert_eq!(Settings::decode(&bytes), Err(SettingsError::BadCrc));
+
+        let mut bytes = TUNED.encode();
+        bytes[0] = b'X';
+        assert_eq!(Settings::decode(&bytes), Err(SettingsError::BadMagic));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = TUNED.encode();
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let crc = crc16(&bytes[..RECORD_SIZE - 2]);
+        bytes[RECORD_SIZE - 2..].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(Settings::decode(&bytes), Err(SettingsError::UnsupportedVersion(VERSION + 1)));
+    }
+
+    #[test]
+    fn keys_get_and_set_by_name() {
+        let mut settings = Settings::DEFAULT;
+        let key = SettingKey::from_name("stick_dead_zone").unwrap();
+        assert_eq!(key.set(&mut settings, 14), Ok(()));
+        assert_eq!(key.get(&settings), 14);
+
+        assert_eq!(key.set(&mut settings, 300), Err(SettingsError::OutOfRange));
+        assert_eq!(SettingKey::ControlLoopHz.set(&mut settings, 0), Err(SettingsError::OutOfRange));
+        assert_eq!(settings, Settings { stick_dead_zone: 14, ..Settings::DEFAULT });
+        assert_eq!(SettingKey::from_name("warp_speed"), None);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let bad = Settings { control_loop_hz: 0, ..TUNED };
+        assert!(!bad.is_valid());
+        assert_eq!(Settings::decode(&bad.encode()), Err(SettingsError::OutOfRange));
+    }
+}
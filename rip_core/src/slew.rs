@@ -0,0 +1,135 @@
+//! Acceleration and braking ramps for motor speeds
+//!
+//! Jumping straight from full reverse to full forward browns out the battery
+//! and can tip the bot, so speeds are walked towards their target at a
+//! limited rate on a fixed tick.
+
+/// Ramp rates in percent of full speed per second; 0 means no limit
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlewRate {
+    /// Rate while speed magnitude is increasing
+    pub accel_per_s: u16,
+    /// Rate while speed magnitude is decreasing, including through a reversal
+    pub decel_per_s: u16,
+}
+
+impl SlewRate {
+    pub const UNLIMITED: Self = SlewRate {
+        accel_per_s: 0,
+        decel_per_s: 0,
+    };
+}
+
+/// Tracks one ramped speed
+///
+/// Internally the speed is kept in thousandths of a percent, so a rate in
+/// percent per second times a step in milliseconds is the change per step.
+pub struct SlewLimiter {
+    rate: SlewRate,
+    current: i32,
+}
+
+impl SlewLimiter {
+    pub const fn new(rate: SlewRate) -> Self {
+        SlewLimiter { rate, current: 0 }
+    }
+
+    pub fn set_rate(&mut self, rate: SlewRate) {
+        self.rate = rate;
+    }
+
+    /// Current speed, -100 to 100
+    pub fn current(&self) -> i8 {
+        (self.current / 1000) as i8
+    }
+
+    /// Drop straight to zero, for braking and disabling
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+
+    /// Move towards `target` by at most `dt_ms` worth of ramp and return the
+    /// new speed
+    ///
+    /// A reversal first brakes down to zero at the deceleration rate; the
+    /// following steps then accelerate the other way.
+    pub fn step(&mut self, target: i8, dt_ms: u32) -> i8 {
+        let target = target.clamp(-100, 100) as i32 * 1000;
+        let current = self.current;
+        if target == current {
+            return self.current();
+        }
+
+        let accelerating = (target > current && current >= 0) || (target < current && current <= 0);
+        let rate = if accelerating {
+            self.rate.accel_per_s
+        } else {
+            self.rate.decel_per_s
+        };
+
+        if rate == 0 {
+            self.current = target;
+            return self.current();
+        }
+
+        let max_delta = rate as i32 * dt_ms as i32;
+        self.current = if accelerating {
+            current + (target - current).clamp(-max_delta, max_delta)
+        } else {
+            // Never cross zero while braking
+            let limit = if current > 0 { target.max(0) } else { target.min(0) };
+            current + (limit - current).clamp(-max_delta, max_delta)
+        };
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: SlewRate = SlewRate {
+        accel_per_s: 200,
+        decel_per_s: 1000,
+    };
+
+    #[test]
+    fn unlimited_rate_jumps_to_target() {
+        let mut slew = SlewLimiter::new(SlewRate::UNLIMITED);
+        assert_eq!(slew.step(-100, 0), -100);
+        assert_eq!(slew.step(100, 0), 100);
+    }
+
+    #[test]
+    fn accelerates_at_accel_rate() {
+        let mut slew = SlewLimiter::new(RATE);
+        // 200 %/s over 10 ms is 2% per step
+        assert_eq!(slew.step(100, 10), 2);
+        assert_eq!(slew.step(100, 10), 4);
+        for _ in 0..100 {
+            slew.step(100, 10);
+        }
+        assert_eq!(slew.current(), 100);
+    }
+
+    #[test]
+    fn reversal_brakes_to_zero_before_accelerating() {
+        let mut slew = SlewLimiter::new(RATE);
+        slew.step(15, 100);
+        assert_eq!(slew.current(), 15);
+
+        // 1000 %/s over 10 ms is 10% per step, stopping at zero
+        assert_eq!(slew.step(-100, 10), 5);
+        assert_eq!(slew.step(-100, 10), 0);
+        assert_eq!(slew.step(-100, 10), -2);
+    }
+
+    #[test]
+    fn reset_stops_immediately() {
+        let mut slew = SlewLimiter::new(RATE);
+        slew.step(100, 1000);
+        slew.reset();
+        assert_eq!(slew.current(), 0);
+    }
+}
@@ -0,0 +1,694 @@
+//! Bot state machine
+//!
+//! Turns controller frames and fault triggers into the hardware events for
+//! Core 1. It holds no hardware and never waits, so the firmware task just
+//! feeds it inputs and forwards the returned [`Outputs`] to the driver tasks.
+
+use crate::config::*;
+use crate::events::{EmergencyReason, LedEvent, ServoEvent, TankDriveEvent, WeaponEvent};
+use crate::input::{process_movement, Buttons, ControllerData};
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BotState {
+    Idle,
+    /// Drive arming countdown; nothing moves until it completes
+    Arming,
+    /// Drive armed; the weapon arms separately, see [`WeaponState`]
+    Combat,
+    /// No fresh controller data; drive is disabled until the driver re-arms
+    LinkLost,
+    Emergency(EmergencyReason),
+}
+
+/// Weapon interlock, only ever past `Safe` while in `BotState::Combat`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WeaponState {
+    Safe,
+    /// Weapon arming countdown; the spinner stays stopped until it completes
+    Arming,
+    Armed,
+}
+
+/// Snapshot of the state machine for other tasks, e.g. controller rumble
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status {
+    pub state: BotState,
+    pub weapon: WeaponState,
+}
+
+impl Status {
+    /// True while either arming countdown is running
+    pub fn counting_down(&self) -> bool {
+        self.state == BotState::Arming || self.weapon == WeaponState::Arming
+    }
+}
+
+/// Events produced by one state machine step, at most one per driver
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Outputs {
+    pub tank: Option<TankDriveEvent>,
+    pub servo: Option<ServoEvent>,
+    pub weapon: Option<WeaponEvent>,
+    pub led: Option<LedEvent>,
+    /// Drive inversion change, for the tank driver ahead of `tank`
+    pub inverted: Option<bool>,
+}
+
+/// True if `data` is recent enough to act on at `now_ms`, and not a
+/// receiver's failsafe values standing in for a lost transmitter
+pub fn is_fresh(data: &ControllerData, now_ms: u64) -> bool {
+    !data.link.failsafe && now_ms.saturating_sub(data.timestamp_ms) <= LINK_LOSS_TIMEOUT_MS
+}
+
+pub struct StateMachine {
+    settings: Settings,
+    state: BotState,
+    link_lost_since_ms: u64,
+    battery_low: bool,
+    /// Last pattern sent to the LED task, so it is only sent on change
+    led: Option<LedEvent>,
+    inverted: bool,
+    /// Inversion last sent to the tank driver, which starts upright
+    drive_inverted: bool,
+    last_buttons: Buttons,
+    weapon: WeaponState,
+    /// When L2 and R2 were first both held past the combat mode pressure
+    arm_hold_since_ms: Option<u64>,
+    /// Start of the running drive or weapon countdown
+    countdown_since_ms: u64,
+    now_ms: u64,
+    /// False while the input core's heartbeat is missing
+    input_alive: bool,
+    /// Bench test speeds per side and when the test ends
+    motor_test: Option<(i8, i8, u64)>,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    pub const fn new() -> Self {
+        StateMachine {
+            settings: Settings::DEFAULT,
+            state: BotState::Idle,
+            link_lost_since_ms: 0,
+            battery_low: false,
+            led: None,
+            inverted: false,
+            drive_inverted: false,
+            last_buttons: Buttons::from_bits(0),
+            weapon: WeaponState::Safe,
+            arm_hold_since_ms: None,
+            countdown_since_ms: 0,
+            now_ms: 0,
+            input_alive: true,
+            motor_test: None,
+        }
+    }
+
+    /// Apply new tuning values from the next update on
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    pub fn state(&self) -> BotState {
+        self.state
+    }
+
+    pub fn weapon_state(&self) -> WeaponState {
+        self.weapon
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            state: self.state,
+            weapon: self.weapon,
+        }
+    }
+
+    /// Show the low battery warning instead of the normal Idle/Combat pattern
+    pub fn set_battery_low(&mut self, low: bool) {
+        self.battery_low = low;
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// The IMU saw the bot flip over or back; overrides any manual toggle
+    pub fn set_flipped(&mut self, flipped: bool) {
+        if flipped != self.inverted {
+            info!("Flip detected, driving {}", if flipped { "inverted" } else { "upright" });
+        }
+        self.inverted = flipped;
+    }
+
+    /// Report whether the input core's heartbeat is still arriving
+    ///
+    /// Without it the last frame cannot be trusted, so this is handled as
+    /// link loss, escalating to a `MissedHeartbeat` emergency if it persists.
+    pub fn set_input_alive(&mut self, alive: bool) {
+        if alive != self.input_alive {
+            if alive {
+                info!("Input core heartbeat back");
+            } else {
+                warn!("Input core heartbeat missed");
+            }
+        }
+        self.input_alive = alive;
+    }
+
+    /// Run the drive sides directly for `duration_ms`, for bench tests
+    ///
+    /// Only accepted while idle, which also means the controller link is
+    /// up: losing it, the kill combo or arming all end the test.
+    pub fn start_motor_test(&mut self, left: i8, right: i8, duration_ms: u64) -> bool {
+        if self.state != BotState::Idle {
+            warn!("Motor test refused in {}", self.state);
+            return false;
+        }
+        info!("Motor test: L={} R={} for {} ms", left, right, duration_ms);
+        self.motor_test = Some((left, right, self.now_ms + duration_ms));
+        true
+    }
+
+    /// Advance the state machine
+    ///
+    /// `frame` is `None` when no controller data arrived within
+    /// `LINK_LOSS_TIMEOUT_MS`. A frame older than that is treated the same:
+    /// this is the deadman switch.
+    pub fn update(&mut self, frame: Option<&ControllerData>, now_ms: u64) -> Outputs {
+        self.now_ms = now_ms;
+        if self.state != BotState::Idle {
+            self.motor_test = None;
+        }
+        let data = match frame.filter(|_| self.input_alive) {
+            Some(data) if is_fresh(data, now_ms) => data,
+            _ => {
+                let mut out = self.link_lost(now_ms);
+                // An escalation to emergency has already picked its LED
+                out.led = out.led.or_else(|| self.led_change());
+                return out;
+            }
+        };
+        let buttons = data.buttons;
+        let pressed = Buttons::from_bits(buttons.bits() & !self.last_buttons.bits());
+        self.last_buttons = buttons;
+        let mut out = Outputs::default();
+
+        // Dedicated kill combo works from any state
+        if buttons.l1() && buttons.r1() && buttons.l2() && buttons.r2() {
+            return self.trigger_emergency(EmergencyReason::ControllerCombo);
+        }
+
+        // Manual override for when the flip detection gets it wrong
+        if pressed.all(INVERT_TOGGLE_BUTTON) {
+            self.inverted = !self.inverted;
+            info!("Drive inversion toggled: {}", self.inverted);
+        }
+        if self.inverted != self.drive_inverted {
+            self.drive_inverted = self.inverted;
+            out.inverted = Some(self.inverted);
+        }
+
+        // State transitions and LED control
+        match self.state {
+            BotState::Idle => {
+                out.tank = Some(TankDriveEvent::Stop);
+                out.weapon = Some(WeaponEvent::Stop);
+
+                match self.motor_test {
+                    Some((left, right, until_ms)) if now_ms < until_ms => {
+                        out.tank = Some(TankDriveEvent::Sides { left, right });
+                    }
+                    Some(_) => {
+                        self.motor_test = None;
+                        info!("Motor test done");
+                    }
+                    None => {}
+                }
+
+                // Arming gesture: hold L2 and R2 hard, then press Start
+                // within the window
+                let pressure = self.settings.combat_mode_pressure;
+                let holding = data.left_trigger >= pressure && data.right_trigger >= pressure;
+                if !holding {
+                    self.arm_hold_since_ms = None;
+                }
+                if holding {
+                    let since = *self.arm_hold_since_ms.get_or_insert(now_ms);
+                    if pressed.start() && now_ms.saturating_sub(since) <= ARM_WINDOW_MS {
+                        self.state = BotState::Arming;
+                        self.countdown_since_ms = now_ms;
+                        self.arm_hold_since_ms = None;
+                        info!("Drive arming...");
+                    }
+                } else if pressed.start() {
+                    info!("Hold L2 and R2, then press Start to arm");
+                }
+            }
+            BotState::Arming => {
+                out.tank = Some(TankDriveEvent::Stop);
+                out.weapon = Some(WeaponEvent::Stop);
+
+                if buttons.select() {
+                    self.state = BotState::Idle;
+                    info!("Arming aborted, IDLE");
+                } else if now_ms.saturating_sub(self.countdown_since_ms) >= ARM_COUNTDOWN_MS {
+                    self.state = BotState::Combat;
+                    self.weapon = WeaponState::Safe;
+                    info!("COMBAT MODE");
+                }
+            }
+            BotState::Combat => {
+                if buttons.select() {
+                    self.state = BotState::Idle;
+                    self.weapon = WeaponState::Safe;
+                    info!("IDLE");
+                    out.tank = Some(TankDriveEvent::Stop);
+                    out.weapon = Some(WeaponEvent::Stop);
+                } else {
+                    // Process movement, servo and weapon in combat mode
+                    out.tank = Some(process_movement(data, &self.settings));
+                    // Stick up is 0 degrees, down is 180
+                    let angle = (127 - data.right_y as i32) * 180 / 254;
+                    out.servo = Some(ServoEvent::SetAngle(angle as u8));
+                    out.weapon = Some(self.update_weapon(data, pressed, now_ms));
+                }
+            }
+            BotState::LinkLost => {
+                // Link is back, but only re-arm on an explicit Select press so
+                // the bot never drives off on its own after a dropout
+                if buttons.select() {
+                    out.tank = Some(TankDriveEvent::Enable);
+                    self.state = BotState::Idle;
+                    info!("Link re-armed, IDLE");
+                }
+            }
+            BotState::Emergency(reason) => {
+                out.tank = Some(TankDriveEvent::Disable);
+                out.weapon = Some(WeaponEvent::Kill);
+
+                if buttons.start() && buttons.select() {
+                    out.tank = Some(TankDriveEvent::Enable);
+                    self.state = BotState::Idle;
+                    info!("Emergency ({}) cleared, IDLE", reason);
+                }
+            }
+        }
+
+        out.led = self.led_change();
+        out
+    }
+
+    /// Weapon interlock step, only called in `Combat` with a fresh frame
+    fn update_weapon(&mut self, data: &ControllerData, pressed: Buttons, now_ms: u64) -> WeaponEvent {
+        if pressed.all(WEAPON_DISARM_BUTTON) && self.weapon != WeaponState::Safe {
+            self.weapon = WeaponState::Safe;
+            info!("Weapon SAFE");
+        }
+
+        match self.weapon {
+            WeaponState::Safe => {
+                if data.buttons.l1() && pressed.all(WEAPON_ARM_BUTTON) {
+                    self.weapon = WeaponState::Arming;
+                    self.countdown_since_ms = now_ms;
+                    info!("Weapon arming...");
+                }
+                WeaponEvent::Stop
+            }
+            WeaponState::Arming => {
+                if now_ms.saturating_sub(self.countdown_since_ms) >= WEAPON_ARM_COUNTDOWN_MS {
+                    self.weapon = WeaponState::Armed;
+                    info!("Weapon ARMED");
+                }
+                WeaponEvent::Stop
+            }
+            WeaponState::Armed => {
+                let throttle = data.right_trigger as u32 * 100 / 255;
+                WeaponEvent::Spin(throttle as u8)
+            }
+        }
+    }
+
+    /// Disable the drive and latch the emergency state
+    ///
+    /// The first reason wins; later triggers while already in an emergency
+    /// are only logged so the LED keeps showing the original cause.
+    pub fn trigger_emergency(&mut self, reason: EmergencyReason) -> Outputs {
+        if let BotState::Emergency(active) = self.state {
+            if active != reason {
+                warn!("Emergency trigger {} ignored, already in emergency ({})", reason, active);
+            }
+            return Outputs::default();
+        }
+
+        error!("EMERGENCY: {} (code {})", reason, reason.code());
+        self.state = BotState::Emergency(reason);
+        self.weapon = WeaponState::Safe;
+        Outputs {
+            tank: Some(TankDriveEvent::Disable),
+            weapon: Some(WeaponEvent::Kill),
+            led: self.led_change(),
+            ..Outputs::default()
+        }
+    }
+
+    /// LED pattern for the current state
+    fn desired_led(&self) -> LedEvent {
+        match self.state {
+            BotState::Arming => self.countdown_led(ARM_COUNTDOWN_MS),
+            BotState::Combat if self.weapon == WeaponState::Arming => {
+                self.countdown_led(WEAPON_ARM_COUNTDOWN_MS)
+            }
+            BotState::Combat if self.weapon == WeaponState::Armed => LedEvent::Solid,
+            BotState::Idle | BotState::Combat if self.battery_low => LedEvent::Warning,
+            BotState::Idle | BotState::Combat if self.inverted => LedEvent::Inverted,
+            BotState::Idle => LedEvent::Off,
+            BotState::Combat => LedEvent::FastBlink,
+            BotState::LinkLost => LedEvent::SlowBlink,
+            BotState::Emergency(reason) => LedEvent::BlinkCode(reason.code()),
+        }
+    }
+
+    /// Whole seconds left of a countdown of `length_ms`, rounded up
+    fn countdown_led(&self, length_ms: u64) -> LedEvent {
+        let remaining_ms = length_ms.saturating_sub(self.now_ms.saturating_sub(self.countdown_since_ms));
+        LedEvent::Countdown(remaining_ms.div_ceil(1000) as u8)
+    }
+
+    /// The LED pattern to send, if it differs from the one last sent
+    fn led_change(&mut self) -> Option<LedEvent> {
+        let led = self.desired_led();
+        if self.led == Some(led) {
+            return None;
+        }
+        self.led = Some(led);
+        Some(led)
+    }
+
+    fn link_lost(&mut self, now_ms: u64) -> Outputs {
+        match self.state {
+            BotState::LinkLost => {
+                if now_ms.saturating_sub(self.link_lost_since_ms) > LINK_LOSS_EMERGENCY_MS {
+                    let reason = if self.input_alive {
+                        EmergencyReason::LinkLossTimeout
+                    } else {
+                        EmergencyReason::MissedHeartbeat
+                    };
+                    return self.trigger_emergency(reason);
+                }
+                Outputs::default()
+            }
+            BotState::Emergency(_) => Outputs::default(),
+            _ => {
+                warn!("Controller link lost, disabling drive");
+                self.state = BotState::LinkLost;
+                self.weapon = WeaponState::Safe;
+                self.link_lost_since_ms = now_ms;
+                Outputs {
+                    tank: Some(TankDriveEvent::Disable),
+                    weapon: Some(WeaponEvent::Kill),
+                    ..Outputs::default()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Buttons, LinkQuality};
+
+    /// Time at which `armed()` leaves the drive armed
+    const ARMED_AT: u64 = 10 + ARM_COUNTDOWN_MS;
+
+    fn pressing(mask: u16, now_ms: u64) -> ControllerData {
+        ControllerData {
+            buttons: Buttons::from_bits(mask),
+            ..ControllerData::neutral(now_ms)
+        }
+    }
+
+    /// L2 and R2 held hard, plus `mask`
+    fn arm_hold(mask: u16, now_ms: u64) -> ControllerData {
+        ControllerData {
+            left_trigger: 255,
+            right_trigger: 255,
+            ..pressing(mask, now_ms)
+        }
+    }
+
+    fn armed() -> StateMachine {
+        let mut sm = StateMachine::new();
+        sm.update(Some(&arm_hold(0, 0)), 0);
+        sm.update(Some(&arm_hold(Buttons::START, 10)), 10);
+        assert_eq!(sm.state(), BotState::Arming);
+        sm.update(Some(&ControllerData::neutral(ARMED_AT)), ARMED_AT);
+        assert_eq!(sm.state(), BotState::Combat);
+        sm
+    }
+
+    #[test]
+    fn start_alone_does_not_arm() {
+        let mut sm = StateMachine::new();
+        sm.update(Some(&pressing(Buttons::START, 0)), 0);
+        assert_eq!(sm.state(), BotState::Idle);
+
+        // Start too long after the hold began
+        sm.update(Some(&arm_hold(0, 10)), 10);
+        sm.update(Some(&arm_hold(Buttons::START, 20 + ARM_WINDOW_MS)), 20 + ARM_WINDOW_MS);
+        assert_eq!(sm.state(), BotState::Idle);
+    }
+
+    #[test]
+    fn arming_counts_down_and_can_be_aborted() {
+        let mut sm = StateMachine::new();
+        sm.update(Some(&arm_hold(0, 0)), 0);
+        let out = sm.update(Some(&arm_hold(Buttons::START, 10)), 10);
+        assert_eq!(out.led, Some(LedEvent::Countdown(3)));
+        assert_eq!(out.tank, Some(TankDriveEvent::Stop));
+        assert!(sm.status().counting_down());
+
+        let out = sm.update(Some(&ControllerData::neutral(1500)), 1500);
+        assert_eq!(out.led, Some(LedEvent::Countdown(2)));
+
+        sm.update(Some(&pressing(Buttons::SELECT, 1600)), 1600);
+        assert_eq!(sm.state(), BotState::Idle);
+    }
+
+    #[test]
+    fn select_returns_to_idle() {
+        let mut sm = armed();
+        let out = sm.update(Some(&pressing(Buttons::SELECT, ARMED_AT + 10)), ARMED_AT + 10);
+        assert_eq!(sm.state(), BotState::Idle);
+        assert_eq!(out.tank, Some(TankDriveEvent::Stop));
+    }
+
+    #[test]
+    fn combat_forwards_movement() {
+        let mut sm = armed();
+        let frame = ControllerData {
+            left_y: 127,
+            ..ControllerData::neutral(ARMED_AT + 10)
+        };
+        let out = sm.update(Some(&frame), ARMED_AT + 10);
+        assert_eq!(out.tank, Some(TankDriveEvent::Move { x: 0, y: 100 }));
+        assert_eq!(out.servo, Some(ServoEvent::SetAngle(90)));
+        assert_eq!(out.weapon, Some(WeaponEvent::Stop));
+    }
+
+    #[test]
+    fn weapon_arms_separately_and_disarms_on_link_loss() {
+        let mut sm = armed();
+        let t = ARMED_AT + 10;
+
+        // Throttle does nothing while safe
+        assert_eq!(sm.update(Some(&arm_hold(0, t)), t).weapon, Some(WeaponEvent::Stop));
+
+        sm.update(Some(&pressing(Buttons::L1 | WEAPON_ARM_BUTTON, t + 10)), t + 10);
+        assert_eq!(sm.weapon_state(), WeaponState::Arming);
+        let t = t + 10 + WEAPON_ARM_COUNTDOWN_MS;
+        let out = sm.update(Some(&arm_hold(0, t)), t);
+        assert_eq!(sm.weapon_state(), WeaponState::Armed);
+        assert_eq!(out.led, Some(LedEvent::Solid));
+        assert_eq!(sm.update(Some(&arm_hold(0, t + 10)), t + 10).weapon, Some(WeaponEvent::Spin(100)));
+
+        let out = sm.update(None, t + 20 + LINK_LOSS_TIMEOUT_MS);
+        assert_eq!(out.weapon, Some(WeaponEvent::Kill));
+        assert_eq!(sm.weapon_state(), WeaponState::Safe);
+    }
+
+    #[test]
+    fn weapon_never_arms_without_drive() {
+        let mut sm = StateMachine::new();
+        let out = sm.update(Some(&pressing(Buttons::L1 | WEAPON_ARM_BUTTON, 0)), 0);
+        assert_eq!(out.weapon, Some(WeaponEvent::Stop));
+        assert_eq!(sm.weapon_state(), WeaponState::Safe);
+
+        let mut sm = armed();
+        sm.update(Some(&pressing(Buttons::L1 | WEAPON_ARM_BUTTON, ARMED_AT + 10)), ARMED_AT + 10);
+        let out = sm.trigger_emergency(EmergencyReason::OverCurrent);
+        assert_eq!(out.weapon, Some(WeaponEvent::Kill));
+        assert_eq!(sm.weapon_state(), WeaponState::Safe);
+    }
+
+    #[test]
+    fn missing_or_stale_frames_disable_drive() {
+        let mut sm = armed();
+        let out = sm.update(None, ARMED_AT + 100);
+        assert_eq!(sm.state(), BotState::LinkLost);
+        assert_eq!(out.tank, Some(TankDriveEvent::Disable));
+
+        let mut sm = armed();
+        let stale = ControllerData::neutral(ARMED_AT);
+        sm.update(Some(&stale), ARMED_AT + LINK_LOSS_TIMEOUT_MS + 1);
+        assert_eq!(sm.state(), BotState::LinkLost);
+
+        // A radio in failsafe keeps sending frames, but they are not the driver's
+        let mut sm = armed();
+        let failsafe = ControllerData {
+            link: LinkQuality { failsafe: true, ..LinkQuality::WIRED },
+            ..ControllerData::neutral(ARMED_AT + 10)
+        };
+        sm.update(Some(&failsafe), ARMED_AT + 10);
+        assert_eq!(sm.state(), BotState::LinkLost);
+    }
+
+    #[test]
+    fn link_loss_needs_explicit_rearm() {
+        let mut sm = armed();
+        sm.update(None, ARMED_AT + 100);
+
+        // Fresh frames alone do not re-arm
+        sm.update(Some(&pressing(Buttons::START, ARMED_AT + 200)), ARMED_AT + 200);
+        assert_eq!(sm.state(), BotState::LinkLost);
+
+        let out = sm.update(Some(&pressing(Buttons::SELECT, ARMED_AT + 300)), ARMED_AT + 300);
+        assert_eq!(sm.state(), BotState::Idle);
+        assert_eq!(out.tank, Some(TankDriveEvent::Enable));
+    }
+
+    #[test]
+    fn prolonged_link_loss_escalates_to_emergency() {
+        let mut sm = armed();
+        let lost = ARMED_AT + 100;
+        sm.update(None, lost);
+        sm.update(None, lost + LINK_LOSS_EMERGENCY_MS);
+        assert_eq!(sm.state(), BotState::LinkLost);
+
+        let out = sm.update(None, lost + 1 + LINK_LOSS_EMERGENCY_MS);
+        assert_eq!(
+            sm.state(),
+            BotState::Emergency(EmergencyReason::LinkLossTimeout)
+        );
+        assert_eq!(out.led, Some(LedEvent::BlinkCode(2)));
+    }
+
+    #[test]
+    fn missed_input_heartbeat_is_link_loss() {
+        let mut sm = armed();
+        let t = ARMED_AT + 10;
+        sm.set_input_alive(false);
+        let out = sm.update(Some(&ControllerData::neutral(t)), t);
+        assert_eq!(sm.state(), BotState::LinkLost);
+        assert_eq!(out.tank, Some(TankDriveEvent::Disable));
+
+        let t = t + 1 + LINK_LOSS_EMERGENCY_MS;
+        sm.update(Some(&ControllerData::neutral(t)), t);
+        assert_eq!(
+            sm.state(),
+            BotState::Emergency(EmergencyReason::MissedHeartbeat)
+        );
+    }
+
+    #[test]
+    fn led_only_sent_on_change() {
+        let mut sm = StateMachine::new();
+        let idle = ControllerData::neutral(0);
+        assert_eq!(sm.update(Some(&idle), 0).led, Some(LedEvent::Off));
+        assert_eq!(sm.update(Some(&idle), 10).led, None);
+
+        sm.set_battery_low(true);
+        assert_eq!(sm.update(Some(&idle), 20).led, Some(LedEvent::Warning));
+
+        let mut sm = armed();
+        let t = ARMED_AT + 10;
+        assert_eq!(sm.update(Some(&ControllerData::neutral(t)), t).led, None);
+        sm.set_battery_low(true);
+        assert_eq!(sm.update(Some(&ControllerData::neutral(t)), t).led, Some(LedEvent::Warning));
+        sm.set_battery_low(false);
+        assert_eq!(sm.update(Some(&ControllerData::neutral(t)), t).led, Some(LedEvent::FastBlink));
+    }
+
+    #[test]
+    fn inversion_follows_flips_and_toggle() {
+        let mut sm = armed();
+        let t = ARMED_AT;
+        sm.set_flipped(true);
+        let out = sm.update(Some(&ControllerData::neutral(t + 10)), t + 10);
+        assert_eq!(out.inverted, Some(true));
+        assert_eq!(out.led, Some(LedEvent::Inverted));
+        assert_eq!(sm.update(Some(&ControllerData::neutral(t + 20)), t + 20).inverted, None);
+
+        // Toggles once per press, not per frame held
+        let toggle = pressing(INVERT_TOGGLE_BUTTON, t + 30);
+        assert_eq!(sm.update(Some(&toggle), t + 30).inverted, Some(false));
+        assert_eq!(sm.update(Some(&toggle), t + 40).inverted, None);
+        assert!(!sm.inverted());
+    }
+
+    #[test]
+    fn kill_combo_latches_first_reason_until_reset() {
+        let mut sm = armed();
+        let t = ARMED_AT;
+        let combo = Buttons::L1 | Buttons::R1 | Buttons::L2 | Buttons::R2;
+        let out = sm.update(Some(&pressing(combo, t + 10)), t + 10);
+        assert_eq!(
+            sm.state(),
+            BotState::Emergency(EmergencyReason::ControllerCombo)
+        );
+        assert_eq!(out.tank, Some(TankDriveEvent::Disable));
+
+        assert_eq!(sm.trigger_emergency(EmergencyReason::OverCurrent), Outputs::default());
+        sm.update(Some(&pressing(Buttons::START, t + 20)), t + 20);
+        assert_eq!(
+            sm.state(),
+            BotState::Emergency(EmergencyReason::ControllerCombo)
+        );
+
+        let out = sm.update(Some(&pressing(Buttons::START | Buttons::SELECT, t + 30)), t + 30);
+        assert_eq!(sm.state(), BotState::Idle);
+        assert_eq!(out.tank, Some(TankDriveEvent::Enable));
+    }
+
+    #[test]
+    fn motor_test_runs_only_while_idle() {
+        let mut sm = armed();
+        assert!(!sm.start_motor_test(50, 0, 1000));
+
+        let mut sm = StateMachine::new();
+        sm.update(Some(&ControllerData::neutral(0)), 0);
+        assert!(sm.start_motor_test(0, -40, 100));
+        assert_eq!(
+            sm.update(Some(&ControllerData::neutral(50)), 50).tank,
+            Some(TankDriveEvent::Sides { left: 0, right: -40 })
+        );
+        assert_eq!(sm.update(Some(&ControllerData::neutral(100)), 100).tank, Some(TankDriveEvent::Stop));
+
+        // Losing the link ends it for good
+        assert!(sm.start_motor_test(30, 30, 1000));
+        sm.update(None, 200);
+        sm.update(Some(&pressing(Buttons::SELECT, 300)), 300);
+        assert_eq!(sm.state(), BotState::Idle);
+        assert_eq!(sm.update(Some(&ControllerData::neutral(400)), 400).tank, Some(TankDriveEvent::Stop));
+    }
+}
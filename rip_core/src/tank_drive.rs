@@ -0,0 +1,428 @@
+//! Tank drive controller, generic over the motor driver
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::{Error, ErrorKind, ErrorType, OutputPin};
+
+use crate::mixing::{invert_mix, spin_mix, tank_mix};
+use crate::motor::{MotorDriver, MotorError};
+use crate::slew::{SlewLimiter, SlewRate};
+
+/// Standby stand-in for drivers without a shared enable pin (e.g. RC ESCs)
+pub struct NoStandby;
+
+impl ErrorType for NoStandby {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoStandby {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Two driver boards' enable pins switched as one, e.g. for four motors
+/// spread over two TB6612FNGs
+pub struct StandbyPair<A, B>(pub A, pub B);
+
+impl<A: OutputPin, B: OutputPin> ErrorType for StandbyPair<A, B> {
+    type Error = ErrorKind;
+}
+
+impl<A: OutputPin, B: OutputPin> OutputPin for StandbyPair<A, B> {
+    fn set_low(&mut self) -> Result<(), ErrorKind> {
+        // Always try both so one failing pin cannot leave the other enabled
+        let a = self.0.set_low().map_err(|e| e.kind());
+        let b = self.1.set_low().map_err(|e| e.kind());
+        a.and(b)
+    }
+
+    fn set_high(&mut self) -> Result<(), ErrorKind> {
+        self.0.set_high().map_err(|e| e.kind())?;
+        self.1.set_high().map_err(|e| e.kind())
+    }
+}
+
+/// Yaw-rate feedback that keeps straight-line driving straight
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeadingHold {
+    /// Correction in percent of speed per 100 deg/s of yaw rate
+    pub gain: i32,
+    /// Largest correction applied to either side, in percent
+    pub max_correction: i8,
+}
+
+/// Differential drive over a left and a right motor driver
+///
+/// Each side is a single [`MotorDriver`]; use `MotorPair` to run front and
+/// back wheels on a side together. `standby` is the driver board's shared
+/// enable pin (TB6612FNG STBY, DRV8833 nSLEEP), active high.
+///
+/// `drive`, `spin` and `stop` set per-side target speeds. With a slew rate
+/// set, the sides only ramp towards their targets as [`Self::tick`] is
+/// called; `brake` and `disable` always take effect immediately.
+///
+/// With [`HeadingHold`] set, driving with no turn input uses the yaw rate
+/// from [`Self::set_yaw_rate`] to steer against drift.
+///
+/// When inverted, drive commands go through [`invert_mix`] so the controls
+/// still feel the same to the driver with the bot upside down.
+pub struct TankDriveController<M, S> {
+    left: M,
+    right: M,
+    standby: S,
+    left_slew: SlewLimiter,
+    right_slew: SlewLimiter,
+    left_target: i8,
+    right_target: i8,
+    /// Per-side duty limits in percent, from current sensing
+    left_limit: u8,
+    right_limit: u8,
+    heading_hold: Option<HeadingHold>,
+    /// True while the driver is commanding a straight line
+    holding: bool,
+    yaw_rate_mdps: i32,
+    inverted: bool,
+}
+
+/// Scale a speed by a duty limit in percent
+fn limit_speed(speed: i8, limit: u8) -> i8 {
+    (speed as i16 * limit.min(100) as i16 / 100) as i8
+}
+
+impl<M: MotorDriver, S: OutputPin> TankDriveController<M, S> {
+    /// Take ownership of the motors and enable the driver
+    pub fn new(left: M, right: M, standby: S) -> Result<Self, MotorError> {
+        let mut tank = TankDriveController {
+            left,
+            right,
+            standby,
+            left_slew: SlewLimiter::new(SlewRate::UNLIMITED),
+            right_slew: SlewLimiter::new(SlewRate::UNLIMITED),
+            left_target: 0,
+            right_target: 0,
+            left_limit: 100,
+            right_limit: 100,
+            heading_hold: None,
+            holding: false,
+            yaw_rate_mdps: 0,
+            inverted: false,
+        };
+        tank.enable()?;
+        Ok(tank)
+    }
+
+    /// Limit how fast each side may speed up and slow down
+    pub fn set_slew_rate(&mut self, rate: SlewRate) {
+        self.left_slew.set_rate(rate);
+        self.right_slew.set_rate(rate);
+    }
+
+    /// Scale each side's output down to a percentage of the commanded speed,
+    /// e.g. to back off a side drawing too much current. Takes effect on the
+    /// next `tick` or drive command.
+    pub fn set_duty_limit(&mut self, left: u8, right: u8) {
+        self.left_limit = left;
+        self.right_limit = right;
+    }
+
+    /// Drive upside down; applies from the next drive command
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Turn heading hold on or off
+    pub fn set_heading_hold(&mut self, hold: Option<HeadingHold>) {
+        self.heading_hold = hold;
+    }
+
+    /// Latest yaw rate from the IMU in millidegrees per second, CCW positive.
+    /// Takes effect on the next `tick` or drive command.
+    pub fn set_yaw_rate(&mut self, yaw_rate_mdps: i32) {
+        self.yaw_rate_mdps = yaw_rate_mdps;
+    }
+
+    /// Ramped speeds of the left and right side, before duty limits
+    pub fn speeds(&self) -> (i8, i8) {
+        (self.left_slew.current(), self.right_slew.current())
+    }
+
+    /// Ramp both sides towards their targets by `dt_ms` worth of slew
+    pub fn tick(&mut self, dt_ms: u32) -> Result<(), MotorError> {
+        let (left_target, right_target) = self.assisted_targets();
+        let left_speed = self.left_slew.step(left_target, dt_ms);
+        let right_speed = self.right_slew.step(right_target, dt_ms);
+        self.left.set_speed(limit_speed(left_speed, self.left_limit))?;
+        self.right.set_speed(limit_speed(right_speed, self.right_limit))
+    }
+
+    /// Targets with the heading hold correction applied
+    fn assisted_targets(&self) -> (i8, i8) {
+        let (left, right) = (self.left_target, self.right_target);
+        let Some(hold) = self.heading_hold.filter(|_| self.holding) else {
+            return (left, right);
+        };
+
+        // Drifting CCW (positive yaw) is countered by speeding up the left
+        // side, whichever way the bot is driving. Upside down the wheels push
+        // on the ground from the other side of their axles, so the same motor
+        // command turns the body, and the gyro fixed to it, the other way.
+        let max = hold.max_correction.clamp(0, 100) as i32;
+        let correction = (self.yaw_rate_mdps / 1000 * hold.gain / 100).clamp(-max, max);
+        let correction = if self.inverted { -correction } else { correction };
+        let adjust = |speed: i8, by: i32| (speed as i32 + by).clamp(-100, 100) as i8;
+        (adjust(left, correction), adjust(right, -correction))
+    }
+
+    fn set_targets(&mut self, left_speed: i8, right_speed: i8, hold: bool) -> Result<(), MotorError> {
+        // Targets are kept in the motors' frame; `assisted_targets` flips the
+        // heading hold correction to match
+        let (left_speed, right_speed) = if self.inverted {
+            invert_mix(left_speed, right_speed)
+        } else {
+            (left_speed, right_speed)
+        };
+        self.left_target = left_speed;
+        self.right_target = right_speed;
+        self.holding = hold;
+        self.tick(0)
+    }
+
+    /// Zero targets and ramps so nothing resumes after a brake or disable
+    fn reset_ramps(&mut self) {
+        self.left_target = 0;
+        self.right_target = 0;
+        self.holding = false;
+        self.left_slew.reset();
+        self.right_slew.reset();
+    }
+
+    /// Control tank drive with omnidirectional movement
+    /// x: -100 to 100 (left to right)
+    /// y: -100 to 100 (backward to forward)
+    ///
+    /// # Differential Drive Mixing
+    ///
+    /// This uses differential drive mixing ([`tank_mix`]) to convert joystick
+    /// inputs (x,y) into individual motor speeds for tank-style movement:
+    ///
+    /// ```text
+    /// left_speed  = forward_speed + turn_speed
+    /// right_speed = forward_speed - turn_speed
+    /// ```
+    ///
+    /// ## Examples:
+    /// - **Straight forward** (y=50, x=0): Both motors at 50% → moves straight
+    /// - **Straight backward** (y=-50, x=0): Both motors at -50% → reverses straight
+    /// - **Turn right** (y=50, x=30): Left at 80%, Right at 20% → curves right
+    /// - **Backward left turn** (y=-40, x=-20): Left at -60%, Right at -20% → reverses while turning left
+    /// - **Spin in place** (y=0, x=50): Left at 50%, Right at -50% → rotates on spot
+    ///
+    /// This allows smooth omnidirectional control from simple forward/turn inputs.
+    pub fn drive(&mut self, x: i8, y: i8) -> Result<(), MotorError> {
+        let (left_speed, right_speed) = tank_mix(x, y);
+        info!("Tank drive: x={}, y={} => L={}, R={}", x, y, left_speed, right_speed);
+        self.set_targets(left_speed, right_speed, x == 0 && y != 0)
+    }
+
+    /// Spin in place (rotate)
+    /// speed: -100 to 100 (CCW to CW)
+    pub fn spin(&mut self, speed: i8) -> Result<(), MotorError> {
+        let (left_speed, right_speed) = spin_mix(speed);
+        self.set_targets(left_speed, right_speed, false)
+    }
+
+    /// Drive each side at its own speed, bypassing the mixer
+    pub fn drive_sides(&mut self, left: i8, right: i8) -> Result<(), MotorError> {
+        self.set_targets(left, right, false)
+    }
+
+    /// Ramp down at the braking rate, then coast
+    pub fn stop(&mut self) -> Result<(), MotorError> {
+        self.set_targets(0, 0, false)
+    }
+
+    pub fn brake(&mut self) -> Result<(), MotorError> {
+        self.reset_ramps();
+        self.left.brake()?;
+        self.right.brake()
+    }
+
+    pub fn disable(&mut self) -> Result<(), MotorError> {
+        // Stop motors before disabling
+        self.reset_ramps();
+        self.left.set_enabled(false)?;
+        self.right.set_enabled(false)?;
+        self.standby.set_low().map_err(|_| MotorError::Pin)
+    }
+
+    pub fn enable(&mut self) -> Result<(), MotorError> {
+        self.standby.set_high().map_err(|_| MotorError::Pin)?;
+        self.left.set_enabled(true)?;
+        self.right.set_enabled(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motor::mock::{MockPin, MockPwm};
+    use crate::motor::Tb6612Motor;
+
+    type Motor = Tb6612Motor<MockPin, MockPin, MockPwm>;
+
+    fn tank() -> TankDriveController<Motor, MockPin> {
+        let motor = || Tb6612Motor::new(MockPin::default(), MockPin::default(), MockPwm::default()).unwrap();
+        TankDriveController::new(motor(), motor(), MockPin::default()).unwrap()
+    }
+
+    /// Signed speed currently applied to a TB6612 channel
+    fn speed(motor: &Motor) -> i16 {
+        let duty = motor.pwm_duty() as i16;
+        match (motor.in1_high(), motor.in2_high()) {
+            (true, false) => duty,
+            (false, true) => -duty,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn drive_applies_mixed_speeds() {
+        let mut tank = tank();
+        tank.drive(30, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (80, 20));
+
+        tank.drive(-20, -40).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-60, -20));
+    }
+
+    #[test]
+    fn spin_counter_rotates_sides() {
+        let mut tank = tank();
+        tank.spin(70).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (70, -70));
+    }
+
+    #[test]
+    fn slew_rate_ramps_on_tick() {
+        let mut tank = tank();
+        tank.set_slew_rate(SlewRate { accel_per_s: 1000, decel_per_s: 2000 });
+
+        tank.drive(0, 100).unwrap();
+        assert_eq!(tank.speeds(), (0, 0));
+        tank.tick(10).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (10, 10));
+
+        tank.stop().unwrap();
+        tank.tick(10).unwrap();
+        assert_eq!(tank.speeds(), (0, 0));
+
+        tank.drive(0, 100).unwrap();
+        tank.tick(50).unwrap();
+        tank.disable().unwrap();
+        assert_eq!(tank.speeds(), (0, 0));
+    }
+
+    #[test]
+    fn duty_limit_scales_each_side() {
+        let mut tank = tank();
+        tank.set_duty_limit(50, 100);
+        tank.drive(0, -80).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-40, -80));
+        assert_eq!(tank.speeds(), (-80, -80));
+
+        tank.set_duty_limit(100, 100);
+        tank.tick(0).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-80, -80));
+    }
+
+    #[test]
+    fn heading_hold_only_corrects_straight_driving() {
+        let mut tank = tank();
+        tank.set_heading_hold(Some(HeadingHold { gain: 50, max_correction: 15 }));
+        // Drifting CCW at 20 deg/s
+        tank.set_yaw_rate(20_000);
+
+        tank.drive(0, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (60, 40));
+        tank.drive(0, -50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-40, -60));
+
+        // Clamped to max_correction
+        tank.set_yaw_rate(-90_000);
+        tank.tick(0).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-65, -35));
+
+        // Any turn input hands control back to the driver
+        tank.drive(20, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (70, 30));
+    }
+
+    #[test]
+    fn heading_hold_opposes_drift_when_inverted() {
+        let mut tank = tank();
+        tank.set_heading_hold(Some(HeadingHold { gain: 50, max_correction: 15 }));
+        tank.set_inverted(true);
+        // Drifting CCW at 20 deg/s by the chassis-fixed gyro
+        tank.set_yaw_rate(20_000);
+
+        // Upside down, reverse on the left motors pushes the chassis' left
+        // side forward, turning it CW by the gyro
+        tank.drive(0, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-60, -40));
+    }
+
+    #[test]
+    fn inverted_drive_swaps_sides() {
+        let mut tank = tank();
+        tank.set_inverted(true);
+        tank.drive(30, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (-20, -80));
+
+        // Heading hold still opposes the drift: reversing upside down while
+        // drifting CW, forward on the left motors pushes the chassis' left
+        // side back, turning it CCW
+        tank.set_heading_hold(Some(HeadingHold { gain: 50, max_correction: 15 }));
+        tank.set_yaw_rate(-20_000);
+        tank.drive(0, -50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (60, 40));
+        tank.set_heading_hold(None);
+
+        tank.set_inverted(false);
+        tank.drive(30, 50).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (80, 20));
+    }
+
+    #[test]
+    fn standby_pair_switches_both_boards() {
+        let mut standby = StandbyPair(MockPin::default(), MockPin::default());
+        standby.set_high().unwrap();
+        assert!(standby.0.high && standby.1.high);
+        standby.set_low().unwrap();
+        assert!(!standby.0.high && !standby.1.high);
+    }
+
+    #[test]
+    fn disable_drops_standby_and_ignores_drive() {
+        let mut tank = tank();
+        assert!(tank.standby.high);
+
+        tank.disable().unwrap();
+        assert!(!tank.standby.high);
+        tank.drive(0, 100).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (0, 0));
+
+        tank.enable().unwrap();
+        tank.drive(0, 100).unwrap();
+        assert_eq!((speed(&tank.left), speed(&tank.right)), (100, 100));
+    }
+}
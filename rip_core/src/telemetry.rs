@@ -0,0 +1,434 @@
+//! Binary telemetry frames for the ground station
+//!
+//! Each frame is self-contained so a receiver can join the stream at any
+//! point and lose nothing but the frame it landed in:
+//!
+//! | bytes | field                                         |
+//! |-------|-----------------------------------------------|
+//! | 2     | sync, `0xA5 0x5A`                             |
+//! | 1     | protocol version                              |
+//! | 1     | message ID                                    |
+//! | 1     | payload length                                |
+//! | 4     | uptime in milliseconds when the frame was sent|
+//! | n     | payload, little endian                        |
+//! | 2     | CRC-16 of everything after the sync bytes     |
+//!
+//! The decoder rejects other versions outright rather than guessing, so bump
+//! [`VERSION`] whenever a payload changes.
+
+use crate::battery::{BatteryLevel, BatteryStatus};
+use crate::crc::crc16;
+use crate::events::EmergencyReason;
+use crate::input::{Buttons, ControllerData, LinkQuality};
+use crate::state::{BotState, Status, WeaponState};
+use crate::timing::LoopStats;
+
+pub const SYNC: [u8; 2] = [0xA5, 0x5A];
+pub const VERSION: u8 = 3;
+
+const HEADER_SIZE: usize = 9;
+const CRC_SIZE: usize = 2;
+const MAX_PAYLOAD: usize = 19;
+
+/// Input RSSI byte when the receiver reports none
+const NO_RSSI: i8 = i8::MIN;
+
+/// Largest frame on the wire, for sizing buffers
+pub const MAX_FRAME_SIZE: usize = HEADER_SIZE + MAX_PAYLOAD + CRC_SIZE;
+
+/// Ramped speeds the tank driver is running, before duty limits
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriveOutput {
+    /// -100 to 100
+    pub left: i8,
+    pub right: i8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Message {
+    /// Latest controller frame, with the uptime it was read at
+    Input(ControllerData),
+    Drive(DriveOutput),
+    Status(Status),
+    Battery(BatteryStatus),
+    Loop(LoopStats),
+}
+
+impl Message {
+    pub fn id(&self) -> u8 {
+        match self {
+            Message::Input(_) => 1,
+            Message::Drive(_) => 2,
+            Message::Status(_) => 3,
+            Message::Battery(_) => 4,
+            Message::Loop(_) => 5,
+        }
+    }
+
+    /// Write the payload; returns its length
+    fn encode(&self, out: &mut [u8; MAX_PAYLOAD]) -> usize {
+        match *self {
+            Message::Input(data) => {
+                out[..6].copy_from_slice(&[
+                    data.left_x as u8,
+                    data.left_y as u8,
+                    data.right_x as u8,
+                    data.right_y as u8,
+                    data.left_trigger,
+                    data.right_trigger,
+                ]);
+                out[6..8].copy_from_slice(&data.buttons.bits().to_le_bytes());
+                out[8..11].copy_from_slice(&[
+                    data.link.percent,
+                    data.link.rssi_dbm.unwrap_or(NO_RSSI) as u8,
+                    data.link.failsafe as u8,
+                ]);
+                out[11..15].copy_from_slice(&data.sequence.to_le_bytes());
+                out[15..19].copy_from_slice(&(data.timestamp_ms as u32).to_le_bytes());
+                19
+            }
+            Message::Drive(drive) => {
+                out[0] = drive.left as u8;
+                out[1] = drive.right as u8;
+                2
+            }
+            Message::Status(status) => {
+                let (state, reason) = match status.state {
+                    BotState::Idle => (0, 0),
+                    BotState::Arming => (1, 0),
+                    BotState::Combat => (2, 0),
+                    BotState::LinkLost => (3, 0),
+                    BotState::Emergency(reason) => (4, reason.code()),
+                };
+                let weapon = match status.weapon {
+                    WeaponState::Safe => 0,
+                    WeaponState::Arming => 1,
+                    WeaponState::Armed => 2,
+                };
+                out[..3].copy_from_slice(&[state, reason, weapon]);
+                3
+            }
+            Message::Battery(battery) => {
+                let millivolts = battery.millivolts.min(u16::MAX as u32) as u16;
+                out[..2].copy_from_slice(&millivolts.to_le_bytes());
+                out[2] = match battery.level {
+                    BatteryLevel::Normal => 0,
+                    BatteryLevel::Low => 1,
+                    BatteryLevel::Critical => 2,
+                    BatteryLevel::Absent => 3,
+                };
+                3
+            }
+            Message::Loop(stats) => {
+                for (i, value) in [stats.iterations, stats.overruns, stats.last_us, stats.max_us]
+                    .into_iter()
+                    .enumerate()
+                {
+                    out[i * 4..][..4].copy_from_slice(&value.to_le_bytes());
+                }
+                16
+            }
+        }
+    }
+
+    fn decode(id: u8, payload: &[u8]) -> Result<Self, TelemetryError> {
+        let expected = match id {
+            1 => 19,
+            2 => 2,
+            3 | 4 => 3,
+            5 => 16,
+            _ => return Err(TelemetryError::UnknownMessage(id)),
+        };
+        if payload.len() != expected {
+            return Err(TelemetryError::BadLength);
+        }
+        let word = |at: usize| u32::from_le_bytes([payload[at], payload[at + 1], payload[at + 2], payload[at + 3]]);
+
+        Ok(match id {
+            1 => Message::Input(ControllerData {
+                left_x: payload[0] as i8,
+                left_y: payload[1] as i8,
+                right_x: payload[2] as i8,
+                right_y: payload[3] as i8,
+                left_trigger: payload[4],
+                right_trigger: payload[5],
+                buttons: Buttons::from_bits(u16::from_le_bytes([payload[6], payload[7]])),
+                link: LinkQuality {
+                    percent: payload[8],
+                    rssi_dbm: Some(payload[9] as i8).filter(|&rssi| rssi != NO_RSSI),
+                    failsafe: payload[10] != 0,
+                },
+                sequence: word(11),
+                timestamp_ms: word(15) as u64,
+            }),
+            2 => Message::Drive(DriveOutput {
+                left: payload[0] as i8,
+                right: payload[1] as i8,
+            }),
+            3 => Message::Status(Status {
+                state: match payload[0] {
+                    0 => BotState::Idle,
+                    1 => BotState::Arming,
+                    2 => BotState::Combat,
+                    3 => BotState::LinkLost,
+                    4 => BotState::Emergency(
+                        EmergencyReason::from_code(payload[1]).ok_or(TelemetryError::BadValue)?,
+                    ),
+                    _ => return Err(TelemetryError::BadValue),
+                },
+                weapon: match payload[2] {
+                    0 => WeaponState::Safe,
+                    1 => WeaponState::Arming,
+                    2 => WeaponState::Armed,
+                    _ => return Err(TelemetryError::BadValue),
+                },
+            }),
+            4 => Message::Battery(BatteryStatus {
+                millivolts: u16::from_le_bytes([payload[0], payload[1]]) as u32,
+                level: match payload[2] {
+                    0 => BatteryLevel::Normal,
+                    1 => BatteryLevel::Low,
+                    2 => BatteryLevel::Critical,
+                    3 => BatteryLevel::Absent,
+                    _ => return Err(TelemetryError::BadValue),
+                },
+            }),
+            _ => Message::Loop(LoopStats {
+                iterations: word(0),
+                overruns: word(4),
+                last_us: word(8),
+                max_us: word(12),
+            }),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TelemetryError {
+    BadCrc,
+    /// Sent by firmware speaking another protocol version
+    UnsupportedVersion(u8),
+    UnknownMessage(u8),
+    /// Payload length does not match the message ID
+    BadLength,
+    /// A field holds a value no encoder produces
+    BadValue,
+}
+
+/// One message with the uptime it was sent at
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame {
+    /// Wraps after 49 days
+    pub timestamp_ms: u32,
+    pub message: Message,
+}
+
+impl Frame {
+    /// Write the frame into `buf`; returns the bytes to send
+    pub fn encode<'a>(&self, buf: &'a mut [u8; MAX_FRAME_SIZE]) -> &'a [u8] {
+        let mut payload = [0; MAX_PAYLOAD];
+        let len = self.message.encode(&mut payload);
+
+        buf[..2].copy_from_slice(&SYNC);
+        buf[2] = VERSION;
+        buf[3] = self.message.id();
+        buf[4] = len as u8;
+        buf[5..9].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        buf[HEADER_SIZE..][..len].copy_from_slice(&payload[..len]);
+        let end = HEADER_SIZE + len;
+        let crc = crc16(&buf[2..end]);
+        buf[end..end + CRC_SIZE].copy_from_slice(&crc.to_le_bytes());
+        &buf[..end + CRC_SIZE]
+    }
+
+    /// Decode one whole frame, sync bytes included
+    fn decode(bytes: &[u8]) -> Result<Self, TelemetryError> {
+        let (body, crc) = bytes[2..].split_at(bytes.len() - 2 - CRC_SIZE);
+        if u16::from_le_bytes([crc[0], crc[1]]) != crc16(body) {
+            return Err(TelemetryError::BadCrc);
+        }
+        if body[0] != VERSION {
+            return Err(TelemetryError::UnsupportedVersion(body[0]));
+        }
+        Ok(Frame {
+            timestamp_ms: u32::from_le_bytes([body[3], body[4], body[5], body[6]]),
+            message: Message::decode(body[1], &body[HEADER_SIZE - 2..])?,
+        })
+    }
+}
+
+/// Reassembles frames from a byte stream
+///
+/// Feed bytes with [`Decoder::push`] and drain with [`Decoder::next_frame`].
+/// After a bad frame it hunts for the next sync bytes from just past the
+/// start of the bad one, so one corrupted byte costs at most that frame.
+pub struct Decoder {
+    buf: [u8; MAX_FRAME_SIZE],
+    len: usize,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Decoder {
+            buf: [0; MAX_FRAME_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        // Only reachable with a garbage length byte, which next_frame drops
+        if self.len == MAX_FRAME_SIZE {
+            self.discard(1);
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    /// The next complete frame or error, if any
+    pub fn next_frame(&mut self) -> Option<Result<Frame, TelemetryError>> {
+        loop {
+            if self.len == 0 {
+                return None;
+            }
+            if self.buf[0] != SYNC[0] || (self.len >= 2 && self.buf[1] != SYNC[1]) {
+                self.discard(1);
+                continue;
+            }
+            if self.len < HEADER_SIZE {
+                return None;
+            }
+
+            let payload_len = self.buf[4] as usize;
+            if payload_len > MAX_PAYLOAD {
+                self.discard(1);
+                return Some(Err(TelemetryError::BadLength));
+            }
+            let total = HEADER_SIZE + payload_len + CRC_SIZE;
+            if self.len < total {
+                return None;
+            }
+
+            let result = Frame::decode(&self.buf[..total]);
+            // A good frame is consumed whole; a bad one might hide a real
+            // frame's sync bytes, so only step past its first byte
+            self.discard(if result.is_ok() { total } else { 1 });
+            return Some(result);
+        }
+    }
+
+    fn discard(&mut self, count: usize) {
+        self.buf.copy_within(count..self.len, 0);
+        self.len -= count;
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> [Frame; 6] {
+        let input = ControllerData {
+            left_x: -116,
+            right_y: 122,
+            left_trigger: 80,
+            link: LinkQuality {
+                percent: 87,
+                rssi_dbm: Some(-72),
+                failsafe: false,
+            },
+            buttons: Buttons::from_bits(Buttons::L1 | Buttons::CROSS),
+            sequence: 70_000,
+            ..ControllerData::neutral(123_456)
+        };
+        [
+            Message::Input(input),
+            Message::Drive(DriveOutput { left: -100, right: 37 }),
+            Message::Status(Status {
+                state: BotState::Combat,
+                weapon: WeaponState::Armed,
+            }),
+            Message::Status(Status {
+                state: BotState::Emergency(EmergencyReason::LowBattery),
+                weapon: WeaponState::Safe,
+            }),
+            Message::Battery(BatteryStatus {
+                millivolts: 11_420,
+                level: BatteryLevel::Low,
+            }),
+            Message::Loop(LoopStats {
+                iterations: 90_000,
+                overruns: 3,
+                last_us: 412,
+                max_us: 1_250,
+            }),
+        ]
+        .map(|message| Frame {
+            timestamp_ms: 98_765,
+            message,
+        })
+    }
+
+    fn decode_all(bytes: &[u8]) -> Vec<Result<Frame, TelemetryError>> {
+        let mut decoder = Decoder::new();
+        let mut out = Vec::new();
+        for &byte in bytes {
+            decoder.push(byte);
+            while let Some(result) = decoder.next_frame() {
+                out.push(result);
+            }
+        }
+        out
+    }
+
+    fn stream(frames: &[Frame]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for frame in frames {
+            bytes.extend_from_slice(frame.encode(&mut [0; MAX_FRAME_SIZE]));
+        }
+        bytes
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let frames = frames();
+        let decoded: Vec<_> = decode_all(&stream(&frames)).into_iter().map(Result::unwrap).collect();
+        assert_eq!(decoded, frames);
+    }
+
+    #[test]
+    fn resyncs_after_noise_and_corruption() {
+        let frames = frames();
+        let mut bytes = vec![0x00, SYNC[0], 0x13, SYNC[0]];
+        bytes.extend(stream(&frames[..2]));
+        // Corrupt the input frame's payload
+        bytes[4 + HEADER_SIZE + 3] ^= 0x40;
+
+        let decoded = decode_all(&bytes);
+        assert!(decoded.contains(&Err(TelemetryError::BadCrc)));
+        assert_eq!(decoded.last(), Some(&Ok(frames[1])));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut buf = [0; MAX_FRAME_SIZE];
+        let len = frames()[1].encode(&mut buf).len();
+        buf[2] = VERSION + 1;
+        let crc = crc16(&buf[2..len - CRC_SIZE]);
+        buf[len - CRC_SIZE..len].copy_from_slice(&crc.to_le_bytes());
+
+        assert_eq!(
+            decode_all(&buf[..len]),
+            [Err(TelemetryError::UnsupportedVersion(VERSION + 1))]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Control loop timing statistics
+
+/// Tracks how long each fixed-rate loop iteration took against its period
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LoopStats {
+    /// Iterations run so far
+    pub iterations: u32,
+    /// Iterations that took longer than the loop period
+    pub overruns: u32,
+    /// Duration of the most recent iteration in microseconds
+    pub last_us: u32,
+    /// Longest iteration seen in microseconds
+    pub max_us: u32,
+}
+
+impl LoopStats {
+    pub const fn new() -> Self {
+        LoopStats {
+            iterations: 0,
+            overruns: 0,
+            last_us: 0,
+            max_us: 0,
+        }
+    }
+
+    /// Record one iteration; returns true if it overran `period_us`
+    pub fn record(&mut self, elapsed_us: u32, period_us: u32) -> bool {
+        self.iterations = self.iterations.wrapping_add(1);
+        self.last_us = elapsed_us;
+        self.max_us = self.max_us.max(elapsed_us);
+
+        let overrun = elapsed_us > period_us;
+        if overrun {
+            self.overruns = self.overruns.wrapping_add(1);
+        }
+        overrun
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_overruns_and_tracks_max() {
+        let mut stats = LoopStats::new();
+        assert!(!stats.record(900, 1000));
+        assert!(stats.record(1500, 1000));
+        assert!(!stats.record(1000, 1000));
+
+        assert_eq!(stats.iterations, 3);
+        assert_eq!(stats.overruns, 1);
+        assert_eq!(stats.max_us, 1500);
+        assert_eq!(stats.last_us, 1000);
+    }
+}
@@ -0,0 +1,125 @@
+//! Heartbeat registry for the hardware watchdog
+//!
+//! Each critical task checks in with the time every loop. The watchdog
+//! feeder only feeds while every task has checked in recently, so a hang in
+//! any of them, on either core, ends in a watchdog reset instead of motors
+//! stuck on their last duty.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Tasks the watchdog waits on
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum WatchedTask {
+    InputReader = 0,
+    StateController = 1,
+    TankDriver = 2,
+}
+
+impl WatchedTask {
+    pub const ALL: [WatchedTask; 3] = [
+        WatchedTask::InputReader,
+        WatchedTask::StateController,
+        WatchedTask::TankDriver,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+/// Check-in value before a task has run at all
+const NEVER: u32 = u32::MAX;
+
+/// Last check-in time of each task, in wrapping milliseconds
+///
+/// Only plain loads and stores, so it works on cores without atomic
+/// read-modify-write and can be shared as a `static` between cores.
+pub struct HeartbeatRegistry {
+    last_ms: [AtomicU32; WatchedTask::ALL.len()],
+    /// How long after boot a task may take to check in for the first time
+    startup_grace_ms: u32,
+}
+
+impl HeartbeatRegistry {
+    pub const fn new(startup_grace_ms: u32) -> Self {
+        HeartbeatRegistry {
+            last_ms: [const { AtomicU32::new(NEVER) }; WatchedTask::ALL.len()],
+            startup_grace_ms,
+        }
+    }
+
+    pub fn check_in(&self, task: WatchedTask, now_ms: u64) {
+        // Keep clear of the sentinel when the clock wraps onto it
+        let now = (now_ms as u32).min(NEVER - 1);
+        self.last_ms[task as usize].store(now, Ordering::Relaxed);
+    }
+
+    /// First task that has not checked in within `timeout_ms`, if any.
+    /// A task that never checked in only counts as stale once the startup
+    /// grace has passed, so slow setup before a task's loop is not a hang.
+    pub fn stale(&self, now_ms: u64, timeout_ms: u32) -> Option<WatchedTask> {
+        WatchedTask::ALL.into_iter().find(|&task| {
+            let last = self.last_ms[task as usize].load(Ordering::Relaxed);
+            if last == NEVER {
+                now_ms > self.startup_grace_ms as u64
+            } else {
+                (now_ms as u32).wrapping_sub(last) > timeout_ms
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_checked_in(registry: &HeartbeatRegistry, now_ms: u64) {
+        for task in WatchedTask::ALL {
+            registry.check_in(task, now_ms);
+        }
+    }
+
+    #[test]
+    fn stale_until_every_task_checks_in() {
+        let registry = HeartbeatRegistry::new(0);
+        registry.check_in(WatchedTask::InputReader, 0);
+        assert_eq!(registry.stale(1, 100), Some(WatchedTask::StateController));
+
+        all_checked_in(&registry, 0);
+        assert_eq!(registry.stale(100, 100), None);
+    }
+
+    #[test]
+    fn slow_startup_is_not_stale_within_grace() {
+        let registry = HeartbeatRegistry::new(1000);
+        registry.check_in(WatchedTask::InputReader, 950);
+        assert_eq!(registry.stale(1000, 100), None);
+
+        // Checked in tasks are judged as usual, waiting ones once grace ends
+        assert_eq!(registry.stale(1001, 100), Some(WatchedTask::StateController));
+        all_checked_in(&registry, 950);
+        assert_eq!(registry.stale(1001, 100), None);
+        assert_eq!(registry.stale(1051, 100), Some(WatchedTask::InputReader));
+    }
+
+    #[test]
+    fn reports_the_task_that_stopped() {
+        let registry = HeartbeatRegistry::new(0);
+        all_checked_in(&registry, 0);
+        registry.check_in(WatchedTask::InputReader, 150);
+        registry.check_in(WatchedTask::StateController, 150);
+        assert_eq!(registry.stale(150, 100), Some(WatchedTask::TankDriver));
+    }
+
+    #[test]
+    fn survives_clock_wrap() {
+        let registry = HeartbeatRegistry::new(0);
+        let before_wrap = u32::MAX as u64 - 10;
+        all_checked_in(&registry, before_wrap);
+        assert_eq!(registry.stale(before_wrap + 50, 100), None);
+        assert_eq!(WatchedTask::from_index(2), Some(WatchedTask::TankDriver));
+        assert_eq!(WatchedTask::from_index(3), None);
+    }
+}
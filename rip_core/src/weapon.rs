@@ -0,0 +1,124 @@
+//! Spinner weapon on a brushless ESC
+//!
+//! The ESC takes standard 1-2 ms RC pulses: 1 ms is stopped, 2 ms is full
+//! throttle. Throttle changes are ramped so the spinner does not brown out
+//! the battery on spin-up; [`WeaponController::kill`] skips the ramp.
+
+use embedded_hal::pwm::SetDutyCycle;
+
+use crate::motor::{pulse_to_duty, MotorError, RC_PULSE_MAX_US, RC_PULSE_MIN_US};
+use crate::slew::{SlewLimiter, SlewRate};
+
+pub struct WeaponController<P> {
+    pwm: P,
+    period_us: u32,
+    ramp: SlewLimiter,
+    target: u8,
+}
+
+impl<P: SetDutyCycle> WeaponController<P> {
+    /// `period_us` is the PWM frame length the slice was configured for.
+    /// `rate` is the spin-up (`accel_per_s`) and spin-down (`decel_per_s`)
+    /// ramp. Starts at minimum throttle, which is also what ESCs expect to
+    /// see at power-on before they arm.
+    pub fn new(pwm: P, period_us: u32, rate: SlewRate) -> Result<Self, MotorError> {
+        let mut weapon = WeaponController {
+            pwm,
+            period_us,
+            ramp: SlewLimiter::new(rate),
+            target: 0,
+        };
+        weapon.kill()?;
+        Ok(weapon)
+    }
+
+    /// Throttle currently output, 0 to 100
+    pub fn speed(&self) -> u8 {
+        self.ramp.current() as u8
+    }
+
+    /// Ramp towards `speed`, 0 to 100
+    pub fn set_speed(&mut self, speed: u8) -> Result<(), MotorError> {
+        self.target = speed.min(100);
+        self.tick(0)
+    }
+
+    /// Ramp down at the spin-down rate
+    pub fn stop(&mut self) -> Result<(), MotorError> {
+        self.set_speed(0)
+    }
+
+    /// Cut to minimum throttle immediately
+    pub fn kill(&mut self) -> Result<(), MotorError> {
+        self.target = 0;
+        self.ramp.reset();
+        self.output(0)
+    }
+
+    /// Advance the ramp by `dt_ms`
+    pub fn tick(&mut self, dt_ms: u32) -> Result<(), MotorError> {
+        let speed = self.ramp.step(self.target as i8, dt_ms);
+        self.output(speed as u8)
+    }
+
+    fn output(&mut self, speed: u8) -> Result<(), MotorError> {
+        let range = RC_PULSE_MAX_US - RC_PULSE_MIN_US;
+        let pulse_us = RC_PULSE_MIN_US + speed.min(100) as u32 * range / 100;
+        let duty = pulse_to_duty(pulse_us, self.period_us, self.pwm.max_duty_cycle());
+        self.pwm.set_duty_cycle(duty).map_err(|_| MotorError::Pwm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motor::mock::MockPwm;
+
+    const RATE: SlewRate = SlewRate {
+        accel_per_s: 100,
+        decel_per_s: 500,
+    };
+
+    /// 20 ms frame with 1 duty count per microsecond
+    fn weapon() -> WeaponController<MockPwm> {
+        let pwm = MockPwm { max: 20_000, duty: 0 };
+        WeaponController::new(pwm, 20_000, RATE).unwrap()
+    }
+
+    #[test]
+    fn starts_at_minimum_throttle() {
+        let weapon = weapon();
+        assert_eq!(weapon.pwm.duty, 1000);
+    }
+
+    #[test]
+    fn spins_up_and_down_on_ramps() {
+        let mut weapon = weapon();
+        weapon.set_speed(100).unwrap();
+        assert_eq!(weapon.speed(), 0);
+        weapon.tick(100).unwrap();
+        assert_eq!(weapon.speed(), 10);
+        assert_eq!(weapon.pwm.duty, 1100);
+
+        weapon.tick(900).unwrap();
+        assert_eq!(weapon.pwm.duty, 2000);
+
+        // Spin-down is faster than spin-up
+        weapon.stop().unwrap();
+        weapon.tick(100).unwrap();
+        assert_eq!(weapon.speed(), 50);
+    }
+
+    #[test]
+    fn kill_skips_the_ramp() {
+        let mut weapon = weapon();
+        weapon.set_speed(80).unwrap();
+        weapon.tick(1000).unwrap();
+        weapon.kill().unwrap();
+        assert_eq!(weapon.speed(), 0);
+        assert_eq!(weapon.pwm.duty, 1000);
+
+        weapon.tick(100).unwrap();
+        assert_eq!(weapon.speed(), 0);
+    }
+}
@@ -0,0 +1,305 @@
+//! Ground station: decode the bot's telemetry stream into CSV or JSON lines
+//!
+//! Usage: `rip_ground [--json] <port or capture>`
+//!
+//! The firmware streams telemetry on its second USB serial port, usually
+//! `/dev/ttyACM1` next to the console on `/dev/ttyACM0`. A file captured
+//! from the port (`cat /dev/ttyACM1 > match.bin`) decodes the same way.
+//!
+//! CSV has one column per field of every message, left empty when the row's
+//! message does not carry it, so it loads straight into a spreadsheet.
+
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+use rip_core::state::BotState;
+use rip_core::telemetry::{Decoder, Frame, Message};
+
+/// A field value; text is quoted in JSON
+enum Value {
+    Number(i64),
+    Text(String),
+}
+
+/// Every column, in output order, after `time_ms` and `message`
+const COLUMNS: [&str; 23] = [
+    "left_x",
+    "left_y",
+    "right_x",
+    "right_y",
+    "left_trigger",
+    "right_trigger",
+    "buttons",
+    "link_percent",
+    "rssi_dbm",
+    "failsafe",
+    "sequence",
+    "input_ms",
+    "drive_left",
+    "drive_right",
+    "state",
+    "reason",
+    "weapon",
+    "battery_mv",
+    "battery_level",
+    "loop_iterations",
+    "loop_overruns",
+    "loop_last_us",
+    "loop_max_us",
+];
+
+fn name(message: &Message) -> &'static str {
+    match message {
+        Message::Input(_) => "input",
+        Message::Drive(_) => "drive",
+        Message::Status(_) => "status",
+        Message::Battery(_) => "battery",
+        Message::Loop(_) => "loop",
+    }
+}
+
+fn fields(message: &Message) -> Vec<(&'static str, Value)> {
+    use Value::{Number, Text};
+
+    match *message {
+        Message::Input(data) => vec![
+            ("left_x", Number(data.left_x.into())),
+            ("left_y", Number(data.left_y.into())),
+            ("right_x", Number(data.right_x.into())),
+            ("right_y", Number(data.right_y.into())),
+            ("left_trigger", Number(data.left_trigger.into())),
+            ("right_trigger", Number(data.right_trigger.into())),
+            ("buttons", Number(data.buttons.bits().into())),
+            ("link_percent", Number(data.link.percent.into())),
+            ("failsafe", Number(data.link.failsafe.into())),
+            ("sequence", Number(data.sequence.into())),
+            ("input_ms", Number(data.timestamp_ms as i64)),
+        ]
+        .into_iter()
+        .chain(data.link.rssi_dbm.map(|rssi| ("rssi_dbm", Number(rssi.into()))))
+        .collect(),
+        Message::Drive(drive) => vec![
+            ("drive_left", Number(drive.left.into())),
+            ("drive_right", Number(drive.right.into())),
+        ],
+        Message::Status(status) => match status.state {
+            BotState::Emergency(reason) => vec![
+                ("state", Text("Emergency".into())),
+                ("reason", Text(format!("{reason:?}"))),
+                ("weapon", Text(format!("{:?}", status.weapon))),
+            ],
+            state => vec![
+                ("state", Text(format!("{state:?}"))),
+                ("weapon", Text(format!("{:?}", status.weapon))),
+            ],
+        },
+        Message::Battery(battery) => vec![
+            ("battery_mv", Number(battery.millivolts.into())),
+            ("battery_level", Text(format!("{:?}", battery.level))),
+        ],
+        Message::Loop(stats) => vec![
+            ("loop_iterations", Number(stats.iterations.into())),
+            ("loop_overruns", Number(stats.overruns.into())),
+            ("loop_last_us", Number(stats.last_us.into())),
+            ("loop_max_us", Number(stats.max_us.into())),
+        ],
+    }
+}
+
+fn csv_header() -> String {
+    format!("time_ms,message,{}", COLUMNS.join(","))
+}
+
+fn csv_line(frame: &Frame) -> String {
+    let fields = fields(&frame.message);
+    let mut line = format!("{},{}", frame.timestamp_ms, name(&frame.message));
+    for column in COLUMNS {
+        line.push(',');
+        match fields.iter().find(|(name, _)| *name == column) {
+            Some((_, Value::Number(n))) => line += &n.to_string(),
+            Some((_, Value::Text(text))) => line += text,
+            None => {}
+        }
+    }
+    line
+}
+
+fn json_line(frame: &Frame) -> String {
+    let mut line = format!(
+        "{{\"time_ms\":{},\"message\":\"{}\"",
+        frame.timestamp_ms,
+        name(&frame.message)
+    );
+    for (name, value) in fields(&frame.message) {
+        match value {
+            Value::Number(n) => line += &format!(",\"{name}\":{n}"),
+            Value::Text(text) => line += &format!(",\"{name}\":\"{text}\""),
+        }
+    }
+    line.push('}');
+    line
+}
+
+/// Decode `input` until it ends, writing one line per frame
+fn run(mut input: impl Read, mut output: impl Write, json: bool) -> std::io::Result<u32> {
+    if !json {
+        writeln!(output, "{}", csv_header())?;
+    }
+
+    let mut decoder = Decoder::new();
+    let mut errors = 0;
+    let mut buf = [0; 256];
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            return Ok(errors);
+        }
+        for &byte in &buf[..n] {
+            decoder.push(byte);
+            while let Some(result) = decoder.next_frame() {
+                match result {
+                    Ok(frame) if json => writeln!(output, "{}", json_line(&frame))?,
+                    Ok(frame) => writeln!(output, "{}", csv_line(&frame))?,
+                    Err(e) => {
+                        errors += 1;
+                        eprintln!("bad frame: {e:?}");
+                    }
+                }
+            }
+        }
+        // Live from a port, show each batch as it arrives
+        output.flush()?;
+    }
+}
+
+/// Serial ports default to line editing, which would mangle binary frames
+fn make_raw(path: &str) {
+    let status = std::process::Command::new("stty").args(["-F", path, "raw", "-echo"]).status();
+    if !matches!(status, Ok(status) if status.success()) {
+        eprintln!("warning: could not put {path} in raw mode, frames may be corrupted");
+    }
+}
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--csv" => json = false,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = &paths[..] else {
+        eprintln!("usage: rip_ground [--json | --csv] <port or capture>");
+        return ExitCode::FAILURE;
+    };
+
+    if path.starts_with("/dev/") {
+        make_raw(path);
+    }
+    let result = std::fs::File::open(path)
+        .and_then(|file| run(file, std::io::stdout().lock(), json))
+        .map_err(|e| format!("{path}: {e}"));
+
+    match result {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(errors) => {
+            eprintln!("{errors} bad frames skipped");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rip_core::battery::{BatteryLevel, BatteryStatus};
+    use rip_core::events::EmergencyReason;
+    use rip_core::input::ControllerData;
+    use rip_core::state::{Status, WeaponState};
+    use rip_core::telemetry::{DriveOutput, MAX_FRAME_SIZE};
+    use rip_core::timing::LoopStats;
+
+    fn frame(timestamp_ms: u32, message: Message) -> Frame {
+        Frame { timestamp_ms, message }
+    }
+
+    fn encode(frames: &[Frame]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for frame in frames {
+            bytes.extend_from_slice(frame.encode(&mut [0; MAX_FRAME_SIZE]));
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8], json: bool) -> Vec<String> {
+        let mut out = Vec::new();
+        run(bytes, &mut out, json).unwrap();
+        String::from_utf8(out).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn stream_decodes_to_json_lines() {
+        let bytes = encode(&[
+            frame(
+                1_000,
+                Message::Status(Status {
+                    state: BotState::Emergency(EmergencyReason::OverCurrent),
+                    weapon: WeaponState::Safe,
+                }),
+            ),
+            frame(1_050, Message::Drive(DriveOutput { left: -40, right: 55 })),
+            frame(
+                1_100,
+                Message::Loop(LoopStats {
+                    iterations: 200,
+                    overruns: 1,
+                    last_us: 310,
+                    max_us: 1_020,
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            decode(&bytes, true),
+            [
+                r#"{"time_ms":1000,"message":"status","state":"Emergency","reason":"OverCurrent","weapon":"Safe"}"#,
+                r#"{"time_ms":1050,"message":"drive","drive_left":-40,"drive_right":55}"#,
+                r#"{"time_ms":1100,"message":"loop","loop_iterations":200,"loop_overruns":1,"loop_last_us":310,"loop_max_us":1020}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_decodes_to_csv_with_fixed_columns() {
+        let mut bytes = encode(&[
+            frame(5, Message::Input(ControllerData { sequence: 9, ..ControllerData::neutral(3) })),
+            frame(
+                7,
+                Message::Battery(BatteryStatus {
+                    millivolts: 11_100,
+                    level: BatteryLevel::Low,
+                }),
+            ),
+        ]);
+        // Noise from joining mid-stream is skipped
+        bytes.splice(0..0, [0x42, 0x5A, 0x00]);
+
+        let lines = decode(&bytes, false);
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+        assert_eq!(
+            lines,
+            [
+                csv_header(),
+                "5,input,0,0,0,0,0,0,0,100,,0,9,3,,,,,,,,,,,".to_string(),
+                "7,battery,,,,,,,,,,,,,,,,,,11100,Low,,,,".to_string(),
+            ]
+        );
+    }
+}